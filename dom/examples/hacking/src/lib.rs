@@ -27,7 +27,7 @@ fn root() -> Div {
 
     root = root.child(mox! { <div>{% "hello world from moxie! ({})", &count }</div> });
     root = root.child(mox! {
-        <button type="button" onclick = move |_| set_count.update(|c| Some(c + 1))>
+        <button type="button" onclick={move |_| { set_count.update(|c| Some(c + 1)); }}>
             "increment"
         </button>
     });
@@ -9,6 +9,7 @@ use gotham::{
     state::{FromState, State},
 };
 use mox::mox;
+use moxie::cache_map;
 use moxie_dom::{
     elements::text_content::{li, ul, Ul},
     embed::DomLoop,
@@ -29,9 +30,13 @@ struct PathExtractor {
 
 #[topo::nested]
 fn simple_list(items: &[String]) -> Ul {
+    // a path can repeat a part, so parts are keyed by their position
+    let parts: Vec<_> = items.iter().cloned().enumerate().collect();
+    let rendered = cache_map(&parts, |(index, _)| *index, |(_, part)| mox!(<li>{ part }</li>));
+
     let mut list = ul();
-    for item in items {
-        list = list.child(mox!(<li>{ item }</li>));
+    for item in rendered.outputs() {
+        list = list.child(item.clone());
     }
     list.build()
 }
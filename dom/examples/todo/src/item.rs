@@ -46,7 +46,7 @@ fn item_with_buttons(todo: Todo, editing: Key<bool>) -> Div {
                     })
                     .collect(),
             )
-        })
+        });
     };
 
     mox! {
@@ -20,7 +20,7 @@ pub fn toggle(default_checked: bool) -> Span {
                     })
                     .collect(),
             )
-        })
+        });
     };
 
     mox! {
@@ -1,102 +1,93 @@
 //! Nodes which cache mutations.
 
 use augdom::{Dom, Node};
-use moxie::cache_with;
-use std::{
-    cell::Cell,
-    fmt::{Debug, Formatter, Result as FmtResult},
-};
+use moxie::tree::{Retained, TreeBackend};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
 
 /// A topologically-nested "incremental smart pointer" for an HTML element.
 ///
 /// Created during execution of the (element) macro and the element-specific
 /// wrappers. Offers a "stringly-typed" API for mutating the contained DOM
 /// nodes, adhering fairly closely to the upstream web specs.
+#[derive(Clone)]
 pub struct CachedNode {
-    id: topo::CallId,
-    last_child: Cell<Option<Node>>,
-    node: Node,
+    inner: Retained<AugDom>,
 }
 
 impl CachedNode {
     #[topo::nested]
     pub(crate) fn new(node: Node) -> Self {
-        Self { node, last_child: Cell::new(None), id: topo::CallId::current() }
+        Self { inner: Retained::new(AugDom, node) }
+    }
+
+    #[topo::nested]
+    pub(crate) fn create_element(ty: &'static str) -> Self {
+        Self { inner: Retained::create(AugDom, &ty) }
     }
 
     pub(crate) fn raw_node(&self) -> &Node {
-        &self.node
+        self.inner.node()
     }
 
     // TODO accept PartialEq+ToString implementors
-    #[topo::nested(slot = "&(self.id, name)")]
     pub(crate) fn set_attribute(&self, name: &'static str, value: &str) {
-        let mut should_set = false;
-        cache_with(
-            value,
-            |_| {
-                // when this isn't the first time the attribute is being set for this element,
-                // this closure executes while the previous attribute's guard is still live.
-                // if we actually set the attribute here, it will be removed when this closure exits
-                // which we definitely don't want. easiest fix is to set the attribute after our
-                // hypothetical cleanup has completed
-                should_set = true;
-                let name = name.to_owned();
-                // TODO find a way to reuse the guard if we're replacing a previous value
-                scopeguard::guard(self.node.clone(), move |node| {
-                    node.remove_attribute(&name);
-                })
-            },
-            |_| {},
-        );
-
-        if should_set {
-            self.node.set_attribute(name, value);
-        }
+        self.inner.set_prop(name, value);
     }
 
     pub(crate) fn ensure_child_attached(&self, new_child: &Node) {
-        let prev_sibling = self.last_child.replace(Some(new_child.clone()));
-
-        let existing = if prev_sibling.is_none() {
-            self.node.first_child()
-        } else {
-            prev_sibling.and_then(|p| p.next_sibling())
-        };
-
-        if let Some(ref existing) = existing {
-            if existing != new_child {
-                self.node.replace_child(new_child, existing);
-            }
-        } else {
-            self.node.append_child(new_child);
-        }
+        self.inner.ensure_child_attached(new_child);
     }
 
     pub(crate) fn remove_trailing_children(&self) {
-        let last_desired_child = self.last_child.replace(None);
-
-        // if there weren't any children declared this revision, we need to
-        // make sure we clean up any from the last revision
-        let mut next_to_remove = if let Some(c) = last_desired_child {
-            // put back the last node we found this revision so this can be called multiple
-            // times
-            self.last_child.set(Some(c.clone()));
-            c.next_sibling()
-        } else {
-            self.node.first_child()
-        };
-
-        while let Some(to_remove) = next_to_remove {
-            next_to_remove = to_remove.next_sibling();
-            self.node.remove_child(&to_remove).unwrap();
-        }
+        self.inner.remove_trailing_children();
     }
 }
 
 impl Debug for CachedNode {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        f.debug_struct("CachedNode").field("node", &self.node).finish()
+        f.debug_struct("CachedNode").field("node", self.raw_node()).finish()
+    }
+}
+
+/// Maintains the augmented DOM, creating elements in the current
+/// [`augdom::document`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct AugDom;
+
+impl TreeBackend for AugDom {
+    type Kind = &'static str;
+    type Node = Node;
+    type Prop = str;
+
+    fn create(&self, ty: &&'static str) -> Node {
+        augdom::document().create_element(ty)
+    }
+
+    fn first_child(&self, parent: &Node) -> Option<Node> {
+        parent.first_child()
+    }
+
+    fn next_sibling(&self, _parent: &Node, node: &Node) -> Option<Node> {
+        node.next_sibling()
+    }
+
+    fn insert(&self, parent: &Node, child: &Node) {
+        parent.append_child(child);
+    }
+
+    fn replace(&self, parent: &Node, new: &Node, existing: &Node) {
+        parent.replace_child(new, existing);
+    }
+
+    fn remove(&self, parent: &Node, child: &Node) {
+        parent.remove_child(child).unwrap();
+    }
+
+    fn set_prop(&self, node: &Node, name: &str, value: Option<&str>) {
+        match value {
+            Some(value) => node.set_attribute(name, value),
+            None => node.remove_attribute(name),
+        }
     }
 }
 
@@ -94,10 +94,8 @@ macro_rules! element {
             #[allow(unused)]
             use crate::interfaces::node::NodeWrapper;
 
-            let elem = moxie::cache(stringify!($name), |ty| {
-                $crate::prelude::document().create_element(ty)
-            });
-            [<$name:camel Builder>] { inner: crate::cached_node::CachedNode::new(elem) }
+            let elem = crate::cached_node::CachedNode::create_element(stringify!($name));
+            [<$name:camel Builder>] { inner: elem }
         }
 
         $(#[$outer])*
@@ -166,6 +164,7 @@ macro_rules! element {
         $(#[$outer])*
         ///
         /// The initialized element, ready to be bound to a parent.
+        #[derive(Clone)]
         #[must_use = "needs to be bound to a parent"]
         pub struct [<$name:camel>] {
             inner: crate::cached_node::CachedNode,
@@ -51,7 +51,7 @@ impl NodeBuilder for CounterBuilder {
         let text = text.unwrap_or_default();
 
         let button = mox! {
-            <button onclick = move |_| set_value.update(|n| Some(n + 1))>
+            <button onclick={move |_| { set_value.update(|n| Some(n + 1)); }}>
                 {% "{} ({})", text, value }
             </button>
         };
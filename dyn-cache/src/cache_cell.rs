@@ -2,51 +2,185 @@ use super::dep_node::{DepNode, Dependent};
 use std::{
     any::type_name,
     borrow::Borrow,
+    cell::Cell,
     fmt::{Debug, Formatter, Result as FmtResult},
 };
 
 /// A CacheCell represents the storage used for a particular input/output pair
 /// on the heap.
-#[derive(Clone, Default, Hash, Eq, PartialEq)]
+#[derive(Clone, Default, Eq, PartialEq)]
 pub(crate) struct CacheCell<Input, Output> {
     dep: DepNode,
     input: Input,
     output: Output,
+    weight: usize,
+    /// only kept by caches with a capacity, which pass a tick of their clock to each access
+    recency: Option<Box<Recency>>,
+}
+
+/// When a cell was used, so that caches with a capacity can evict the least
+/// recently used cells first.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct Recency {
+    /// the cache's clock when this cell was last accessed
+    last_used: Cell<u64>,
+    /// the revision in which a read of this cell missed, until it's stored again
+    pending_since: Cell<Option<u64>>,
 }
 
 impl<Input, Output> CacheCell<Input, Output> {
-    pub fn new(input: Input, output: Output, dep: DepNode) -> Self {
-        Self { dep, input, output }
+    pub fn new(
+        input: Input,
+        output: Output,
+        weight: usize,
+        dep: DepNode,
+        tick: Option<u64>,
+    ) -> Self {
+        let recency =
+            tick.map(|tick| Box::new(Recency { last_used: Cell::new(tick), ..Default::default() }));
+        Self { dep, input, output, weight, recency }
+    }
+
+    /// Create a cell which will be dropped by the next GC unless it's read
+    /// before then.
+    #[cfg(feature = "serde")]
+    pub fn dead(
+        input: Input,
+        output: Output,
+        weight: usize,
+        revision: u64,
+        tick: Option<u64>,
+    ) -> Self {
+        let mut dep = DepNode::new(Dependent::default(), revision);
+        dep.mark_dead();
+        Self::new(input, output, weight, dep, tick)
     }
 
     /// Return a reference to the output if the input is equal, marking it live
     /// in the process. If get fails, returns its own `Dependent` to be used as
     /// a dependency of any queries which are invoked to re-initialize this
-    /// cell.
-    pub fn get<Arg>(&self, input: &Arg, dependent: Dependent) -> Result<&Output, Dependent>
+    /// cell, and marks the cell pending until it's stored again.
+    pub fn get<Arg>(
+        &self,
+        input: &Arg,
+        dependent: Dependent,
+        revision: u64,
+        tick: Option<u64>,
+    ) -> Result<&Output, Dependent>
     where
         Arg: PartialEq<Input> + ?Sized,
         Input: Borrow<Arg>,
     {
         self.dep.root_read(dependent);
+        self.used(tick);
         if input == &self.input {
             Ok(&self.output)
         } else {
+            self.mark_pending(revision);
             Err(self.dep.as_dependent())
         }
     }
 
-    /// Store a new input/output and mark the storage live.
-    pub fn store(&mut self, input: Input, output: Output, dependent: Dependent, revision: u64) {
+    /// Store a new input/output and mark the storage live, returning the
+    /// previous input/output.
+    pub fn store(
+        &mut self,
+        input: Input,
+        output: Output,
+        weight: usize,
+        dependent: Dependent,
+        revision: u64,
+        tick: Option<u64>,
+    ) -> (Input, Output) {
+        self.dep.root_write(dependent, revision);
+        self.stored(tick);
+        self.weight = weight;
+        (std::mem::replace(&mut self.input, input), std::mem::replace(&mut self.output, output))
+    }
+
+    /// Return a mutable reference to the output regardless of the input,
+    /// marking it live in the process. Also returns the cell's own `Dependent`
+    /// in case the output needs to be updated.
+    pub fn get_mut(&mut self, dependent: Dependent, tick: Option<u64>) -> (&mut Output, Dependent) {
+        self.dep.root_read(dependent);
+        self.used(tick);
+        (&mut self.output, self.dep.as_dependent())
+    }
+
+    /// Return mutable references to the output and its weight for an in-place
+    /// update and mark the storage live.
+    pub fn update(
+        &mut self,
+        dependent: Dependent,
+        revision: u64,
+        tick: Option<u64>,
+    ) -> (&mut Output, &mut usize) {
         self.dep.root_write(dependent, revision);
-        self.input = input;
-        self.output = output;
+        self.stored(tick);
+        (&mut self.output, &mut self.weight)
+    }
+
+    /// Mark this cell as being re-initialized by a query which missed during
+    /// `revision`, so that it isn't evicted before the query stores its result.
+    pub fn mark_pending(&self, revision: u64) {
+        if let Some(recency) = &self.recency {
+            recency.pending_since.set(Some(revision));
+        }
+    }
+
+    /// Returns true if a query which missed during `revision` hasn't yet stored
+    /// its result to this cell.
+    pub fn is_pending(&self, revision: u64) -> bool {
+        self.recency.as_ref().is_some_and(|r| r.pending_since.get() == Some(revision))
+    }
+
+    fn used(&self, tick: Option<u64>) {
+        if let (Some(recency), Some(tick)) = (&self.recency, tick) {
+            recency.last_used.set(tick);
+        }
+    }
+
+    fn stored(&self, tick: Option<u64>) {
+        self.used(tick);
+        if let Some(recency) = &self.recency {
+            recency.pending_since.set(None);
+        }
+    }
+
+    /// Returns the weight of the stored output.
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    /// Returns the order in which this cell should be evicted relative to
+    /// others: unused cells before live ones, least recently used first.
+    pub fn eviction_order(&self) -> (bool, u64) {
+        (self.is_live(), self.recency.as_ref().map_or(0, |r| r.last_used.get()))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    pub fn into_output(self) -> Output {
+        self.output
     }
 
     pub fn is_live(&self) -> bool {
         self.dep.is_known_live()
     }
 
+    /// Returns true if another cached value which depends on this one still
+    /// exists.
+    pub fn is_referenced(&self) -> bool {
+        self.dep.has_dependents()
+    }
+
     pub fn update_liveness(&mut self, current_revision: u64) {
         self.dep.update_liveness(current_revision);
     }
@@ -54,6 +188,10 @@ impl<Input, Output> CacheCell<Input, Output> {
     pub fn mark_dead(&mut self) {
         self.dep.mark_dead();
     }
+
+    pub fn mark_removed(&self) {
+        self.dep.mark_removed();
+    }
 }
 
 impl<Input, Output> Debug for CacheCell<Input, Output>
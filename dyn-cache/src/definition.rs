@@ -60,10 +60,24 @@ paste::item! {
         $refct:ident,
         $lock:ident :: $acquire:ident
     ) => {
-use crate::{dep_node::Dependent, *};
+use crate::{dep_node::Dependent, lru::Lru, *};
 use hash_hasher::HashBuildHasher;
-use hashbrown::HashMap;
-use std::{any::TypeId, borrow::Borrow, cmp::{Eq, Ordering}, hash::{Hash, Hasher}};
+use hashbrown::{hash_map::RawEntryMut, HashMap};
+use std::{
+    any::{Any, TypeId},
+    borrow::Borrow,
+    cell::Cell,
+    cmp::{Eq, Ordering},
+    collections::VecDeque,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+#[cfg(feature = "serde")]
+use serde_crate::{
+    de::{self, DeserializeOwned},
+    ser::SerializeMap,
+    Deserializer, Serialize, Serializer,
+};
 
 doc_comment! {"
 Holds arbitrary query results which are namespaced by arbitrary scope types. Usually used
@@ -96,6 +110,31 @@ referenced since the prior call.
 After each GC, all values still in the cache are marked garbage. They are marked live again when
 inserted with [`" stringify!($cache) "::store`] or read with
 [`" stringify!($cache) "::get`].
+
+# Capacity
+
+A cache created with [`" stringify!($cache) "::with_capacity`] also evicts values between GCs
+whenever the total weight of its stored outputs exceeds the capacity. Values which haven't been
+marked live since the last GC are evicted first, followed by the least recently used live values,
+until the total weight is at most seven eighths of the capacity, so that storing a few more
+values doesn't immediately evict again. The value which was just stored is never evicted, nor are
+values whose queries are still being re-initialized.
+
+Each output weighs 1 unless a weigher is registered for its type with
+[`" stringify!($cache) "::set_weigher`]. The number of values evicted is reported by
+[`" stringify!($cache) "::stats`].
+
+# Eviction listeners
+
+Listeners registered with [`" stringify!($cache) "::on_evict`] receive the scope and output of
+each value of their query type when it's removed from the cache.
+
+# Persistence
+
+With the `serde` feature enabled, values of the query types registered with `persist` can be
+written out with `save` and read back into another cache with `load`, for example to reuse them
+after the process restarts. Loaded values are marked garbage, so they're dropped by the first GC
+after they're loaded unless they're read before then.
 "=>
 #[derive(Debug, Default)]
 pub struct $cache {
@@ -103,9 +142,623 @@ pub struct $cache {
     /// are globally unique and pre-hashed courtesy of rustc.
     inner: HashMap<TypeId, Box<dyn Storage $(+ $bound)?>, HashBuildHasher>,
     revision: u64,
+    /// Orders accesses to stored values so that the least recently used can be evicted first.
+    clock: Cell<u64>,
+    capacity: Option<usize>,
+    /// Boxed `Fn(&Output) -> usize`s, indexed by the `TypeId` of `Output`.
+    weighers: HashMap<TypeId, Box<dyn Any $(+ $bound)?>, HashBuildHasher>,
+    /// Formatters for scopes in debug output, indexed by the `TypeId` of `Scope`.
+    scope_formatters: HashMap<TypeId, DebugScope, HashBuildHasher>,
+    /// Eviction listeners, indexed by the `TypeId` of their query.
+    listeners: HashMap<TypeId, $refct<dyn Notify $(+ $bound + Sync)?>, HashBuildHasher>,
+    /// Query types registered for persistence, indexed by their `TypeId`.
+    #[cfg(feature = "serde")]
+    persisted: HashMap<TypeId, Persisted, HashBuildHasher>,
 }}
 
+/// An eviction listener registered with [`" stringify!($cache) "::on_evict`], shared by the
+/// storage for values of its query type and for those stored by `cache_lru_with`.
+struct OnEvict<Scope, Output> {
+    inner: $lock<Delivery<Scope, Output>>,
+}
+
+/// A listener and the removals waiting to be passed to it.
+struct Delivery<Scope, Output> {
+    /// `None` while the listener is being called.
+    listener: Option<Box<dyn FnMut(Scope, Output) $(+ $bound)?>>,
+    pending: VecDeque<(Scope, Output)>,
+}
+
+impl<Scope, Output> OnEvict<Scope, Output> {
+    fn new(listener: Box<dyn FnMut(Scope, Output) $(+ $bound)?>) -> Self {
+        Self { inner: $lock::new(Delivery { listener: Some(listener), pending: VecDeque::new() }) }
+    }
+
+    /// Pass `removals` to the listener without holding any lock while it runs, so that it and the
+    /// destructors of the outputs passed to it may remove more values of its query type. Those
+    /// removals are queued and passed to the listener after the current call returns.
+    fn deliver(&self, removals: impl IntoIterator<Item = (Scope, Output)>) {
+        let mut listener = {
+            let mut delivery = self.inner.$acquire();
+            delivery.pending.extend(removals);
+            match delivery.listener.take() {
+                Some(listener) => listener,
+                // whoever is calling the listener will pass these along
+                None => return,
+            }
+        };
+
+        loop {
+            let next = {
+                let mut delivery = self.inner.$acquire();
+                match delivery.pending.pop_front() {
+                    Some(next) => next,
+                    None => {
+                        delivery.listener = Some(listener);
+                        return;
+                    }
+                }
+            };
+            let (scope, output) = next;
+            listener(scope, output);
+        }
+    }
+}
+
+/// Passes values removed from the storage for a query type to its listener.
+struct Listener<Scope, Input, Output> {
+    on_evict: $refct<OnEvict<Scope, Output>>,
+    input: PhantomData<fn() -> Input>,
+}
+
+impl<Scope, Input, Output> Notify for Listener<Scope, Input, Output>
+where
+    Scope: 'static,
+    Input: 'static,
+    Output: 'static,
+{
+    fn notify(&self, removed: Box<dyn Any>) {
+        let removed = removed.downcast::<Removal<Scope, Input, Output>>()
+            .expect("listeners are indexed by their query");
+        self.on_evict.deliver(Some((*removed).into_scope_output()));
+    }
+}
+
+impl<Scope, Input, Output> std::fmt::Debug for Listener<Scope, Input, Output> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Listener")
+            .field("scope", &std::any::type_name::<Scope>())
+            .field("output", &std::any::type_name::<Output>())
+            .finish()
+    }
+}
+
+/// Passes each output removed from the storage used by `cache_lru_with` for a query type to its
+/// listener.
+struct LruListener<Scope, Input, Output> {
+    on_evict: $refct<OnEvict<Scope, Output>>,
+    input: PhantomData<fn() -> Input>,
+}
+
+impl<Scope, Input, Output> Notify for LruListener<Scope, Input, Output>
+where
+    Scope: Clone + 'static,
+    Input: 'static,
+    Output: 'static,
+{
+    fn notify(&self, removed: Box<dyn Any>) {
+        let removed = removed.downcast::<Removal<Scope, (), Lru<Input, Output>>>()
+            .expect("listeners are indexed by their query");
+        let (scope, lru) = (*removed).into_scope_output();
+        let mut outputs = lru.into_outputs().collect::<Vec<_>>();
+        let last = outputs.pop();
+
+        let mut removals = outputs
+            .into_iter()
+            .map(|output| (scope.clone(), output))
+            .collect::<Vec<_>>();
+        removals.extend(last.map(|output| (scope, output)));
+        self.on_evict.deliver(removals);
+    }
+}
+
+impl<Scope, Input, Output> std::fmt::Debug for LruListener<Scope, Input, Output> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("LruListener")
+            .field("scope", &std::any::type_name::<Scope>())
+            .field("output", &std::any::type_name::<Output>())
+            .finish()
+    }
+}
+
+/// Values removed from the cache, paired with the listeners to notify once the cache is no longer
+/// locked.
+struct Evictions(Vec<(Option<$refct<dyn Notify $(+ $bound + Sync)?>>, Box<dyn Any>)>);
+
+impl Evictions {
+    /// Pass each value to its listener, if any, dropping the rest.
+    fn notify(self) {
+        for (listener, value) in self.0 {
+            if let Some(listener) = listener {
+                listener.notify(value);
+            }
+        }
+    }
+}
+
+/// Saves and loads the values of a query type registered with `persist`.
+#[cfg(feature = "serde")]
+trait Persist: std::fmt::Debug {
+    /// Returns the name under which the query's values are saved.
+    fn name(&self) -> &str;
+
+    /// Copy the query's stored values so that they can be serialized after the cache is
+    /// unlocked, if it has any.
+    fn save(&self, cache: &$cache) -> Option<Box<dyn erased_serde::Serialize>>;
+
+    /// Deserialize saved values, returning them for [`Persist::load`].
+    fn parse(
+        &self,
+        saved: &mut dyn erased_serde::Deserializer<'_>,
+    ) -> Result<Box<dyn Any>, erased_serde::Error>;
+
+    /// Store values returned by [`Persist::parse`] in `cache`.
+    fn load(&self, cache: &mut $cache, parsed: Box<dyn Any>);
+}
+
+/// A persisted query type, shared by the caches which have registered it.
+#[cfg(feature = "serde")]
+type Persisted = $refct<dyn Persist $(+ $bound + Sync)?>;
+
+/// The values of each persisted query type copied by `saved`, sorted by the query's name.
+#[cfg(feature = "serde")]
+struct Saved(Vec<(String, Box<dyn erased_serde::Serialize>)>);
+
+#[cfg(feature = "serde")]
+impl Serialize for Saved {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, values) in &self.0 {
+            map.serialize_entry(name, values)?;
+        }
+        map.end()
+    }
+}
+
+/// Deserializes a map from query names to saved values, parsing the values of each query type
+/// with its persister.
+#[cfg(feature = "serde")]
+struct Parser<'a>(&'a [Persisted]);
+
+#[cfg(feature = "serde")]
+impl<'de> de::Visitor<'de> for Parser<'_> {
+    type Value = Vec<(Persisted, Box<dyn Any>)>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a map from query names to saved values")
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut parsed = Vec::new();
+        while let Some(name) = map.next_key::<String>()? {
+            match self.0.iter().find(|persister| persister.name() == name) {
+                Some(persister) => {
+                    let values = map.next_value_seed(Parse(&**persister))?;
+                    parsed.push((persister.clone(), values));
+                }
+                None => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Deserializes the saved values of a single query type with its persister.
+#[cfg(feature = "serde")]
+struct Parse<'a>(&'a dyn Persist);
+
+#[cfg(feature = "serde")]
+impl<'de> de::DeserializeSeed<'de> for Parse<'_> {
+    type Value = Box<dyn Any>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let mut erased = <dyn erased_serde::Deserializer>::erase(deserializer);
+        self.0.parse(&mut erased).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct Persister<Scope, Input, Output> {
+    name: String,
+    ty: PhantomData<fn() -> (Scope, Input, Output)>,
+}
+
+#[cfg(feature = "serde")]
+impl<Scope, Input, Output> Persist for Persister<Scope, Input, Output>
+where
+    Scope: 'static + Clone + Eq + Hash + Serialize + DeserializeOwned $(+ $bound)?,
+    Input: 'static + Clone + Serialize + DeserializeOwned $(+ $bound)?,
+    Output: 'static + Clone + Serialize + DeserializeOwned $(+ $bound)?,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn save(&self, cache: &$cache) -> Option<Box<dyn erased_serde::Serialize>> {
+        let query = Query::<Scope, Input, Output>::new(cache.inner.hasher());
+        let namespace = cache.get_namespace(&query)?;
+        let entries = namespace.entries().map(|(scope, input, output)| {
+            (scope.clone(), input.clone(), output.clone())
+        });
+        Some(Box::new(entries.collect::<Vec<_>>()))
+    }
+
+    fn parse(
+        &self,
+        saved: &mut dyn erased_serde::Deserializer<'_>,
+    ) -> Result<Box<dyn Any>, erased_serde::Error> {
+        Ok(Box::new(erased_serde::deserialize::<Vec<(Scope, Input, Output)>>(saved)?))
+    }
+
+    fn load(&self, cache: &mut $cache, parsed: Box<dyn Any>) {
+        let entries: Vec<(Scope, Input, Output)> =
+            *parsed.downcast().expect("parsed values are returned by the same persister");
+        let revision = cache.revision;
+        // loaded values count as used before any others
+        let tick = cache.capacity.map(|_| 0);
+        let query = Query::<Scope, Input, Output>::new(cache.inner.hasher());
+        for (scope, input, output) in entries {
+            let weight = cache.weigh(&output);
+            cache.get_namespace_mut(&query).load(scope, input, output, weight, revision, tick);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<Scope, Input, Output> std::fmt::Debug for Persister<Scope, Input, Output> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Persister")
+            .field("name", &self.name)
+            .field("scope", &std::any::type_name::<Scope>())
+            .field("input", &std::any::type_name::<Input>())
+            .field("output", &std::any::type_name::<Output>())
+            .finish()
+    }
+}
+
 impl $cache {
+doc_comment! {"
+Create an empty cache which evicts values whenever the total weight of its outputs exceeds
+`capacity`. See [the type-level docs](" stringify!($cache) "#capacity) for details.
+
+# Example
+
+```
+use dyn_cache::" stringify!($module) "::{" stringify!($cache) ", " stringify!($shared) "};
+
+let mut cache = " stringify!($cache) "::with_capacity(10);
+cache.set_weigher(|s: &String| s.len());
+let storage = " stringify!($shared) r#"::from(cache);
+
+storage.hold(&'a', &(), |()| String::from("hello"));
+storage.hold(&'b', &(), |()| String::from("world"));
+assert_eq!(storage.stats().weight(), 10);
+
+// 'a' is the least recently used, so it's evicted to make room for 'c'
+storage.hold(&'c', &(), |()| String::from("!"));
+assert_eq!(storage.stats().weight(), 6);
+assert_eq!(storage.stats().evicted(), 1);
+```
+"#=>
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { capacity: Some(capacity), ..Default::default() }
+    }}
+
+doc_comment! {"
+Weigh each `Output` stored afterwards with `weigher` rather than counting it as 1, replacing any
+weigher previously registered for `Output`. Outputs stored by
+[`" stringify!($shared) "::cache_lru_with`] are weighed individually.
+"=>
+    pub fn set_weigher<Output>(
+        &mut self,
+        weigher: impl Fn(&Output) -> usize + 'static $(+ $bound)?,
+    ) where
+        Output: 'static,
+    {
+        let weigher: Box<dyn Fn(&Output) -> usize $(+ $bound)?> = Box::new(weigher);
+        self.weighers.insert(TypeId::of::<Output>(), Box::new(weigher));
+    }}
+
+doc_comment! {"
+Include each `Scope` in the cache's [`Debug`](std::fmt::Debug) output and in the
+[`VisitedEntry`]s passed to [`" stringify!($cache) "::visit`].
+
+# Example
+
+```
+use dyn_cache::" stringify!($module) "::{" stringify!($cache) ", " stringify!($shared) "};
+
+let mut cache = " stringify!($cache) "::default();
+cache.debug_scopes::<char>();
+let storage = " stringify!($shared) r#"::from(cache);
+
+storage.hold(&'a', &(), |()| ());
+assert!(format!("{:?}", storage).contains("\"scopes\": ['a']"));
+```
+"#=>
+    pub fn debug_scopes<Scope>(&mut self)
+    where
+        Scope: std::fmt::Debug + 'static,
+    {
+        let debug: DebugScope = |scope, f| {
+            std::fmt::Debug::fmt(
+                scope.downcast_ref::<Scope>().expect("formatters are indexed by their scope type"),
+                f,
+            )
+        };
+        let scope_type = TypeId::of::<Scope>();
+        self.inner.values_mut().for_each(|ns| ns.debug_scopes(scope_type, debug));
+        self.scope_formatters.insert(scope_type, debug);
+    }}
+
+    fn weigh<Output: 'static>(&self, output: &Output) -> usize {
+        match self.weighers.get(&TypeId::of::<Output>()) {
+            Some(weigher) => weigher
+                .downcast_ref::<Box<dyn Fn(&Output) -> usize $(+ $bound)?>>()
+                .expect("weighers are indexed by the type they weigh")(output),
+            None => 1,
+        }
+    }
+
+doc_comment! {"
+Call `listener` with the scope and output of each value stored by queries with the given `Scope`,
+`Input` and `Output` types when it's removed from the cache, replacing any listener previously
+registered for the query type.
+
+Values are passed to the listener when they're collected by
+[`" stringify!($cache) "::gc`], evicted to stay within the cache's capacity, replaced by a value
+for a new input, invalidated, or cleared. Outputs stored by
+[`" stringify!($shared) "::cache_lru_with`] are also passed to listeners, including when they're
+no longer among the most recently used for their scope, each with a clone of the scope.
+
+Listeners registered with a [`" stringify!($shared) "`] are called after it has been unlocked, so
+they may access the cache. Values of the listener's query type which are removed while it's being
+called, whether by the listener or by the destructor of an output passed to it, are passed to it
+after the current call returns.
+
+# Example
+
+```
+use std::sync::{Arc, Mutex};
+
+let storage = dyn_cache::" stringify!($module) "::" stringify!($shared) r#"::default();
+let evicted = Arc::new(Mutex::new(Vec::new()));
+let evicted2 = evicted.clone();
+storage.on_evict::<char, u32, String>(move |scope, output| {
+    evicted2.lock().unwrap().push((scope, output));
+});
+
+storage.hold(&'a', &1u32, |n| n.to_string());
+storage.hold(&'a', &2u32, |n| n.to_string());
+assert_eq!(*evicted.lock().unwrap(), [('a', String::from("1"))], "replaced by a new input");
+
+storage.gc();
+storage.gc();
+assert_eq!(evicted.lock().unwrap()[1], ('a', String::from("2")), "collected by gc");
+```
+"#=>
+    pub fn on_evict<Scope, Input, Output>(
+        &mut self,
+        listener: impl FnMut(Scope, Output) + 'static $(+ $bound)?,
+    ) where
+        Scope: Clone + 'static $(+ $bound)?,
+        Input: 'static $(+ $bound)?,
+        Output: 'static $(+ $bound)?,
+    {
+        let on_evict = $refct::new(OnEvict::<Scope, Output>::new(Box::new(listener)));
+        let query = TypeId::of::<(Scope, Input, Output)>();
+        let lru_query = TypeId::of::<(Scope, (), Lru<Input, Output>)>();
+        self.inner.values_mut().for_each(|ns| {
+            ns.listen(query);
+            ns.listen(lru_query);
+        });
+
+        let lru = LruListener::<Scope, Input, Output> {
+            on_evict: on_evict.clone(),
+            input: PhantomData,
+        };
+        let listener = Listener::<Scope, Input, Output> { on_evict, input: PhantomData };
+        self.listeners.insert(query, $refct::new(listener));
+        self.listeners.insert(lru_query, $refct::new(lru));
+    }}
+
+doc_comment! {"
+Include values stored by queries with the given `Scope`, `Input` and `Output` types in
+[`" stringify!($cache) "::save`] and restore them in [`" stringify!($cache) "::load`]. Query types
+must be registered explicitly because the cache can't otherwise serialize the values it stores.
+Values are cloned when they're saved, so that a [`" stringify!($shared) "`] can write them after
+releasing its lock.
+
+Values are saved under `name`, which identifies the query type to the caches that load them. It
+should stay the same across builds of the program which share saved values. Outputs stored by
+[`" stringify!($shared) "::cache_lru_with`] are not saved.
+
+# Example
+
+```
+let storage = dyn_cache::" stringify!($module) "::" stringify!($shared) r#"::default();
+storage.persist::<char, u32, String>("numbers");
+storage.hold(&'a', &1u32, |n| n.to_string());
+
+let mut saved = Vec::new();
+storage.save(&mut serde_json::Serializer::new(&mut saved)).unwrap();
+
+let restored = dyn_cache::"# stringify!($module) "::" stringify!($shared) r#"::default();
+restored.persist::<char, u32, String>("numbers");
+restored.load(&mut serde_json::Deserializer::from_slice(&saved)).unwrap();
+assert_eq!(restored.cache(&'a', &1u32, |_| String::from("recomputed")), "1");
+restored.gc();
+assert_eq!(restored.stats().len(), 1, "loaded values are retained once they're read");
+
+let unread = dyn_cache::"# stringify!($module) "::" stringify!($shared) r#"::default();
+unread.persist::<char, u32, String>("numbers");
+unread.load(&mut serde_json::Deserializer::from_slice(&saved)).unwrap();
+assert_eq!(unread.stats().len(), 1);
+unread.gc();
+assert_eq!(unread.stats().len(), 0, "loaded values are dropped by the next GC unless read");
+```
+
+# Panics
+
+If another query type is already registered under `name`.
+"#=>
+    #[cfg(feature = "serde")]
+    pub fn persist<Scope, Input, Output>(&mut self, name: impl Into<String>)
+    where
+        Scope: 'static + Clone + Eq + Hash + Serialize + DeserializeOwned $(+ $bound)?,
+        Input: 'static + Clone + Serialize + DeserializeOwned $(+ $bound)?,
+        Output: 'static + Clone + Serialize + DeserializeOwned $(+ $bound)?,
+    {
+        let name = name.into();
+        let query = TypeId::of::<(Scope, Input, Output)>();
+        let mut others = self.persisted.iter().filter(|(other, _)| **other != query);
+        assert!(
+            others.all(|(_, persister)| persister.name() != name),
+            "another query type is already persisted as {:?}",
+            name,
+        );
+        let persister = Persister::<Scope, Input, Output> { name, ty: PhantomData };
+        self.persisted.insert(query, $refct::new(persister));
+    }}
+
+doc_comment! {"
+Serialize the values stored by each query type registered with
+[`" stringify!($cache) "::persist`] with `serializer`, as a map from each query type's name to
+its values.
+"=>
+    #[cfg(feature = "serde")]
+    pub fn save<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.saved().serialize(serializer)
+    }}
+
+    /// Copies the values of each persisted query type which has any.
+    #[cfg(feature = "serde")]
+    fn saved(&self) -> Saved {
+        let mut saved = self
+            .persisted
+            .values()
+            .filter_map(|persister| Some((persister.name().to_owned(), persister.save(self)?)))
+            .collect::<Vec<_>>();
+        saved.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Saved(saved)
+    }
+
+doc_comment! {"
+Deserialize values written by [`" stringify!($cache) "::save`] from `deserializer`, storing those
+of each query type registered with [`" stringify!($cache) "::persist`]. Values whose scope already
+has a value in this cache are ignored.
+
+Saved values of query types which aren't registered are skipped, which is only possible in
+self-describing formats such as JSON. Formats like bincode can load saved values as long as every
+query type they include is registered.
+
+Loaded values are marked garbage, so they're dropped by the next GC unless they're read before
+then. If the values of any registered query type can't be deserialized, none are loaded.
+"=>
+    #[cfg(feature = "serde")]
+    pub fn load<'de, D: Deserializer<'de>>(&mut self, deserializer: D) -> Result<(), D::Error> {
+        let parsed = Self::parse_saved(&self.persisters(), deserializer)?;
+        self.notifying(|cache, removed| cache.load_removing(parsed, removed));
+        Ok(())
+    }}
+
+    /// Returns the persisters of each registered query type.
+    #[cfg(feature = "serde")]
+    fn persisters(&self) -> Vec<Persisted> {
+        self.persisted.values().cloned().collect()
+    }
+
+    /// Deserialize the values saved by each of `persisters`' query types.
+    #[cfg(feature = "serde")]
+    fn parse_saved<'de, D: Deserializer<'de>>(
+        persisters: &[Persisted],
+        deserializer: D,
+    ) -> Result<Vec<(Persisted, Box<dyn Any>)>, D::Error> {
+        deserializer.deserialize_map(Parser(persisters))
+    }
+
+    #[cfg(feature = "serde")]
+    fn load_removing(&mut self, parsed: Vec<(Persisted, Box<dyn Any>)>, removed: &mut Removed) {
+        for (persister, values) in parsed {
+            persister.load(self, values);
+        }
+        self.evict_over_capacity(removed);
+    }
+
+    /// Pair each removed value with the listener for its query, if any.
+    fn listen(&self, removed: Removed) -> Evictions {
+        Evictions(
+            removed
+                .values
+                .into_iter()
+                .map(|(query, value)| (self.listeners.get(&query).cloned(), value))
+                .collect(),
+        )
+    }
+
+    /// Run `op`, then notify listeners of any values it removed.
+    fn notifying<R>(&mut self, op: impl FnOnce(&mut Self, &mut Removed) -> R) -> R {
+        let mut removed = Removed::default();
+        let ret = op(self, &mut removed);
+        self.listen(removed).notify();
+        ret
+    }
+
+    /// Advance the clock, returning the time of the current access. Only caches with a capacity
+    /// keep a clock, as it's only used to evict the least recently used values.
+    fn tick(&self) -> Option<u64> {
+        self.capacity?;
+        let tick = self.clock.get();
+        self.clock.set(tick + 1);
+        Some(tick)
+    }
+
+    /// If the total weight of the cache exceeds its capacity, evict values until it's back under
+    /// the low-water mark.
+    fn evict_over_capacity(&mut self, removed: &mut Removed) {
+        let capacity = match self.capacity {
+            Some(capacity) => capacity,
+            None => return,
+        };
+        let mut weight: usize = self.inner.values().map(|ns| ns.weight()).sum();
+        if weight <= capacity {
+            return;
+        }
+
+        // evicting past the capacity leaves room for later stores without sorting again
+        let low_water = capacity - capacity / 8;
+        let revision = self.revision;
+        let mut candidates = Vec::new();
+        self.inner.values().for_each(|ns| ns.eviction_candidates(revision, &mut candidates));
+        candidates.sort_unstable();
+        // the most recently stored value is last, and we always keep it
+        candidates.pop();
+
+        let mut cutoff = None;
+        for (order, value_weight) in candidates {
+            if weight <= low_water {
+                break;
+            }
+            weight -= value_weight;
+            cutoff = Some(order);
+        }
+        if let Some(cutoff) = cutoff {
+            self.inner.values_mut().for_each(|ns| ns.evict(cutoff, revision, removed));
+        }
+    }
+
 doc_comment! {"
 Return a reference to a query's stored output if a result is stored *and* `arg` equals the
 previously-stored `Input`. If a reference is returned, the stored input/output
@@ -130,7 +783,7 @@ a [`CacheEntry`] to pass to [`" stringify!($cache) "::store`].
         let query = Query::new(self.inner.hasher());
 
         if let Some(ns) = self.get_namespace(&query) {
-            ns.get(key, arg, dependent, self.revision)
+            ns.get(key, arg, dependent, self.revision, self.tick())
                 .map_err(|key_miss| CacheMiss { query, key_miss })
         } else {
             let key_miss = KeyMiss::just_key(key, arg.to_owned(), dependent, self.revision);
@@ -152,13 +805,65 @@ Call [`" stringify!($cache) "::get`] to get a [`CacheMiss`] and [`CacheMiss::ini
         Input: 'static $(+ $bound)?,
         Output: 'static $(+ $bound)?,
     {
-        let revision = self.revision; // avoid double-borrowing self
+        self.notifying(|cache, removed| cache.store_removing(entry, removed));
+    }}
+
+    fn store_removing<Key, Scope, Input, Output>(
+        &mut self,
+        entry: CacheEntry<'_, Key, Scope, Input, Output>,
+        removed: &mut Removed,
+    ) where
+        Key: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+        Scope: 'static + Borrow<Key> + Eq + Hash $(+ $bound)?,
+        Input: 'static $(+ $bound)?,
+        Output: 'static $(+ $bound)?,
+    {
+        let (revision, tick) = (self.revision, self.tick()); // avoid double-borrowing self
         let CacheEntry {
             miss: CacheMiss { query, key_miss },
             output,
         } = entry;
-        self.get_namespace_mut(&query).store(key_miss, output, revision);
-    }}
+        let weight = self.weigh(&output);
+        self.get_namespace_mut(&query).store(key_miss, output, weight, revision, tick, removed);
+        self.evict_over_capacity(removed);
+    }
+
+    fn get_lru<'k, Key, Scope, Arg, Input, Output>(
+        &mut self,
+        key: &'k Key,
+        arg: &Arg,
+    ) -> Result<&Output, KeyMiss<'k, Key, Input, DefaultHashBuilder>>
+    where
+        Key: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+        Scope: 'static + Borrow<Key> + Eq + Hash $(+ $bound)?,
+        Arg: PartialEq<Input> + ToOwned<Owned=Input> + ?Sized,
+        Input: 'static $(+ $bound)?,
+        Output: 'static $(+ $bound)?,
+    {
+        let (revision, tick) = (self.revision, self.tick());
+        let query = Query::<Scope, (), Lru<Input, Output>>::new(self.inner.hasher());
+        self.get_namespace_mut(&query).get_lru(key, arg, Dependent::incoming(), revision, tick)
+    }
+
+    fn store_lru<Key, Scope, Input, Output>(
+        &mut self,
+        miss: KeyMiss<'_, Key, Input, DefaultHashBuilder>,
+        output: Output,
+        capacity: usize,
+        removed: &mut Removed,
+    ) where
+        Key: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+        Scope: 'static + Borrow<Key> + Eq + Hash $(+ $bound)?,
+        Input: 'static $(+ $bound)?,
+        Output: 'static $(+ $bound)?,
+    {
+        let (revision, tick) = (self.revision, self.tick());
+        let weight = self.weigh(&output);
+        let query = Query::<Scope, (), Lru<Input, Output>>::new(self.inner.hasher());
+        let namespace = self.get_namespace_mut(&query);
+        namespace.store_lru(miss, output, weight, capacity, revision, tick, removed);
+        self.evict_over_capacity(removed);
+    }
 
     fn get_namespace<Scope, Input, Output>(
         &self,
@@ -185,23 +890,220 @@ Call [`" stringify!($cache) "::get`] to get a [`CacheMiss`] and [`CacheMiss::ini
         Input: 'static $(+ $bound)?,
         Output: 'static $(+ $bound)?,
     {
+        let debug_scope = self.scope_formatters.get(&TypeId::of::<Scope>()).copied();
+        let listened = self.listeners.contains_key(&query.ty());
         let gc: &mut dyn Storage = &mut **self
             .inner
             .raw_entry_mut()
             .from_hash(query.hash(), |t| t == &query.ty())
             .or_insert_with(|| {
-                (query.ty(), query.make_namespace())
+                (query.ty(), query.make_namespace(debug_scope, listened))
             }).1;
         gc.as_any_mut().downcast_mut().unwrap()
     }
 
     /// Drop any values which have not been marked alive since the last call to this method.
     pub fn gc(&mut self) {
+        self.notifying(Self::gc_removing);
+    }
+
+    fn gc_removing(&mut self, removed: &mut Removed) {
         let prev = self.revision; // avoid double-borrowing self
         self.inner.values_mut().for_each(|ns| ns.mark(prev));
-        self.inner.values_mut().for_each(|namespace| namespace.sweep());
+        self.inner.values_mut().for_each(|namespace| namespace.sweep(removed));
         self.revision += 1;
     }
+
+    /// Summarize the values stored for each query type. See [`CacheStats`].
+    pub fn stats(&self) -> CacheStats {
+        let namespaces = self.inner.values().map(|ns| ns.stats()).collect();
+        CacheStats::new(self.revision, self.capacity, namespaces)
+    }
+
+    /// Pass each query type and stored value to `visitor`. See [`CacheVisitor`].
+    pub fn visit(&self, visitor: &mut impl CacheVisitor) {
+        let mut namespaces = self.inner.values().collect::<Vec<_>>();
+        namespaces.sort_by_cached_key(|ns| {
+            let stats = ns.stats();
+            (stats.scope_type(), stats.input_type(), stats.output_type())
+        });
+        for namespace in namespaces {
+            namespace.visit(visitor);
+        }
+    }
+
+    /// Drop all stored values. Each value is dropped before any values which were cached while
+    /// initializing it, so that outer queries are torn down before the inner queries on which
+    /// they depend.
+    pub fn clear(&mut self) {
+        self.notifying(Self::clear_removing);
+    }
+
+    fn clear_removing(&mut self, removed: &mut Removed) {
+        loop {
+            let before = removed.len();
+            self.inner.values_mut().for_each(|ns| ns.drop_unreferenced(removed));
+            if removed.len() == before {
+                break;
+            }
+        }
+        // anything left over is part of a dependency cycle
+        self.inner.values_mut().for_each(|ns| ns.remove_where(&mut |_| true, removed));
+        self.inner.clear();
+    }
+
+doc_comment! {"
+Drop the value stored for `key` by queries with the given `Scope`, `Input` and `Output` types,
+including any stored by [`" stringify!($shared) "::cache_lru_with`], so that it's re-initialized
+the next time it's requested. Returns true if a value was dropped.
+
+Values which read the dropped value while they were initialized are not invalidated.
+
+# Example
+
+```
+let storage = dyn_cache::" stringify!($module) "::" stringify!($shared) r#"::default();
+let call_count = std::cell::Cell::new(0);
+let increment = |&n: &u32| {
+    call_count.set(call_count.get() + 1);
+    n + 1
+};
+
+assert_eq!(storage.cache(&'a', &1, increment), 2);
+assert_eq!(storage.cache(&'a', &1, increment), 2);
+assert_eq!(call_count.get(), 1);
+
+assert!(storage.invalidate::<char, u32, u32, _>(&'a'));
+assert!(!storage.invalidate::<char, u32, u32, _>(&'a'), "already dropped");
+
+assert_eq!(storage.cache(&'a', &1, increment), 2);
+assert_eq!(call_count.get(), 2, "re-initialized after invalidation");
+```
+"#=>
+    pub fn invalidate<Scope, Input, Output, Key>(&mut self, key: &Key) -> bool
+    where
+        Key: Eq + Hash + ?Sized,
+        Scope: 'static + Borrow<Key> + Eq + Hash $(+ $bound)?,
+        Input: 'static $(+ $bound)?,
+        Output: 'static $(+ $bound)?,
+    {
+        self.notifying(|cache, removed| {
+            cache.remove::<Scope, Input, Output, Key>(key, removed);
+            !removed.is_empty()
+        })
+    }}
+
+doc_comment! {"
+Drop every value stored by queries with the given `Scope`, `Input` and `Output` types,
+including any stored by [`" stringify!($shared) "::cache_lru_with`]. Returns the number of
+values dropped.
+"=>
+    pub fn invalidate_namespace<Scope, Input, Output>(&mut self) -> usize
+    where
+        Scope: 'static,
+        Input: 'static,
+        Output: 'static,
+    {
+        self.notifying(|cache, removed| {
+            cache.remove_namespace::<Scope, Input, Output>(removed);
+            removed.len()
+        })
+    }}
+
+doc_comment! {"
+Drop every value whose scope is a `Scope` for which `matches` returns true, regardless of its
+query's input and output types. Returns the number of values dropped.
+
+# Example
+
+```
+let storage = dyn_cache::" stringify!($module) "::" stringify!($shared) r#"::default();
+storage.hold(&1u32, &(), |()| 'a');
+storage.hold(&2u32, &'b', |&c| c.to_string());
+storage.hold(&3u32, &(), |()| 'c');
+
+assert_eq!(storage.invalidate_where(|&n: &u32| n < 3), 2);
+assert_eq!(storage.stats().len(), 1);
+```
+"#=>
+    pub fn invalidate_where<Scope>(&mut self, mut matches: impl FnMut(&Scope) -> bool) -> usize
+    where
+        Scope: 'static,
+    {
+        self.notifying(|cache, removed| {
+            cache.remove_where(&mut matches, removed);
+            removed.len()
+        })
+    }}
+
+    fn remove<Scope, Input, Output, Key>(&mut self, key: &Key, removed: &mut Removed)
+    where
+        Key: Eq + Hash + ?Sized,
+        Scope: 'static + Borrow<Key> + Eq + Hash $(+ $bound)?,
+        Input: 'static $(+ $bound)?,
+        Output: 'static $(+ $bound)?,
+    {
+        if let Some(ns) = self.existing_namespace_mut::<Scope, Input, Output>() {
+            ns.remove(key, removed);
+        }
+        if let Some(ns) = self.existing_namespace_mut::<Scope, (), Lru<Input, Output>>() {
+            ns.remove(key, removed);
+        }
+    }
+
+    fn remove_namespace<Scope, Input, Output>(&mut self, removed: &mut Removed)
+    where
+        Scope: 'static,
+        Input: 'static,
+        Output: 'static,
+    {
+        let plain = self.take_namespace(&Query::<Scope, Input, Output>::new(self.inner.hasher()));
+        let lru = self.take_namespace(&Query::<Scope, (), Lru<Input, Output>>::new(self.inner.hasher()));
+        for mut namespace in plain.into_iter().chain(lru) {
+            namespace.remove_where(&mut |_| true, removed);
+        }
+    }
+
+    fn remove_where<Scope>(&mut self, matches: &mut impl FnMut(&Scope) -> bool, removed: &mut Removed)
+    where
+        Scope: 'static,
+    {
+        let mut matches = |scope: &dyn Any| scope.downcast_ref().map_or(false, &mut *matches);
+        self.inner.values_mut().for_each(|ns| ns.remove_where(&mut matches, removed));
+    }
+
+    fn existing_namespace_mut<Scope, Input, Output>(
+        &mut self,
+    ) -> Option<&mut Namespace<Scope, Input, Output>>
+    where
+        Scope: 'static,
+        Input: 'static,
+        Output: 'static,
+    {
+        let query = Query::<Scope, Input, Output>::new(self.inner.hasher());
+        match self.inner.raw_entry_mut().from_hash(query.hash(), |t| t == &query.ty()) {
+            RawEntryMut::Occupied(occ) => {
+                let gc: &mut dyn Storage = &mut **occ.into_mut();
+                Some(gc.as_any_mut().downcast_mut().unwrap())
+            }
+            RawEntryMut::Vacant(_) => None,
+        }
+    }
+
+    fn take_namespace<Scope, Input, Output>(
+        &mut self,
+        query: &Query<Scope, Input, Output>,
+    ) -> Option<Box<dyn Storage $(+ $bound)?>>
+    where
+        Scope: 'static,
+        Input: 'static,
+        Output: 'static,
+    {
+        match self.inner.raw_entry_mut().from_hash(query.hash(), |t| t == &query.ty()) {
+            RawEntryMut::Occupied(occ) => Some(occ.remove()),
+            RawEntryMut::Vacant(_) => None,
+        }
+    }
 }
 
 impl std::panic::UnwindSafe for $cache {}
@@ -216,6 +1118,11 @@ For convenience wrappers around [`" stringify!($shared) "::cache_with`] see
 `Clone` and [`" stringify!($shared) "::hold`] for values that just need to be stored
 without returning a reference.
 
+# Dropping values
+
+Values removed from the cache, whether they're collected, evicted, invalidated or cleared, are
+dropped after releasing the lock on the cache, so their destructors may access it.
+
 # Example
 
 ```
@@ -308,63 +1215,298 @@ See [`" stringify!($shared) "::cache`] for an ergonomic wrapper that requires `O
             (store, ret)
         });
 
-        self.inner.$acquire().store(to_store);
-        to_return
-    }}
+        self.removing(|cache, removed| cache.store_removing(to_store, removed));
+        to_return
+    }}
+
+doc_comment!{r"
+Caches the result of `init(arg)` once per `key`, re-running it when `arg` changes. Clones
+the cached output before returning the result.
+
+See [`" stringify!($shared) "::cache_with`] for a lower-level version which does not require
+`Output: Clone`.
+"=>
+    pub fn cache<Key, Scope, Arg, Input, Output>(
+        &self,
+        key: &Key,
+        arg: &Arg,
+        init: impl FnOnce(&Input) -> Output,
+    ) -> Output
+    where
+        Key: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+        Scope: 'static + Borrow<Key> + Eq + Hash $(+ $bound)?,
+        Arg: PartialEq<Input> + ToOwned<Owned=Input> + ?Sized,
+        Input: 'static + Borrow<Arg> $(+ $bound)?,
+        Output: 'static + Clone $(+ $bound)?,
+    {
+        self.cache_with(key, arg, init, Clone::clone)
+    }}
+
+doc_comment!{r"
+Caches the result of `init(arg)` once per `key`, re-running it when `arg` changes.
+
+Does not return any reference to the cached value. See [`" stringify!($shared) "::cache`]
+for similar functionality that returns a copy of `Output` or
+[`" stringify!($shared) "::cache_with`] which allows specifying other pre-return functions.
+"=>
+    pub fn hold<Key, Scope, Arg, Input, Output>(
+        &self,
+        key: &Key,
+        arg: &Arg,
+        init: impl FnOnce(&Input) -> Output,
+    )
+    where
+        Key: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+        Scope: 'static + Borrow<Key> + Eq + Hash $(+ $bound)?,
+        Arg: PartialEq<Input> + ToOwned<Owned=Input> + ?Sized,
+        Input: 'static + Borrow<Arg> $(+ $bound)?,
+        Output: 'static $(+ $bound)?,
+    {
+        self.cache_with(key, arg, init, |_| {})
+    }}
+
+doc_comment!{r"
+Caches the results of `init(arg)` for up to `capacity` distinct values of `arg` per `key`,
+evicting the least recently used result when a new `arg` would exceed `capacity`. Always runs
+`with` on the stored `Output` before returning the result.
+
+Unlike [`" stringify!($shared) "::cache_with`], returning to a recently used `arg` doesn't
+re-run `init`. All of a `key`'s results are retained or dropped together by
+[`" stringify!($shared) "::gc`].
+
+See [`" stringify!($shared) "::cache_lru`] for an ergonomic wrapper that requires
+`Output: Clone`.
+
+# Panics
+
+If `capacity` is zero.
+"=>
+    pub fn cache_lru_with<Key, Scope, Arg, Input, Output, Ret>(
+        &self,
+        key: &Key,
+        arg: &Arg,
+        capacity: usize,
+        init: impl FnOnce(&Input) -> Output,
+        with: impl FnOnce(&Output) -> Ret,
+    ) -> Ret
+    where
+        Key: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+        Scope: 'static + Borrow<Key> + Eq + Hash $(+ $bound)?,
+        Arg: PartialEq<Input> + ToOwned<Owned=Input> + ?Sized,
+        Input: 'static $(+ $bound)?,
+        Output: 'static $(+ $bound)?,
+        Ret: 'static $(+ $bound)?,
+    {
+        assert!(capacity > 0, "an LRU cache must be able to hold at least one value");
+        let miss = match { self.inner.$acquire().get_lru(key, arg) } {
+            Ok(stored) => return with(stored),
+            Err(m) => m,
+        };
+
+        let (to_store, to_return) = miss.init(|arg| {
+            let store = init(arg);
+            let ret = with(&store);
+            (store, ret)
+        });
+
+        self.removing(|cache, removed| cache.store_lru(miss, to_store, capacity, removed));
+        to_return
+    }}
+
+doc_comment!{r"
+Caches the results of `init(arg)` for up to `capacity` distinct values of `arg` per `key`,
+evicting the least recently used result when a new `arg` would exceed `capacity`. Clones the
+cached output before returning the result.
+
+See [`" stringify!($shared) "::cache_lru_with`] for a lower-level version which does not
+require `Output: Clone`.
+
+# Example
+
+```
+let storage = dyn_cache::" stringify!($module) "::" stringify!($shared) r#"::default();
+let call_count = std::cell::Cell::new(0);
+let square = |&n: &u32| {
+    call_count.set(call_count.get() + 1);
+    n * n
+};
+
+assert_eq!(storage.cache_lru(&'a', &2, 2, square), 4);
+assert_eq!(storage.cache_lru(&'a', &3, 2, square), 9);
+assert_eq!(storage.cache_lru(&'a', &2, 2, square), 4);
+assert_eq!(call_count.get(), 2, "both inputs are still cached");
 
-doc_comment!{r"
-Caches the result of `init(arg)` once per `key`, re-running it when `arg` changes. Clones
-the cached output before returning the result.
+assert_eq!(storage.cache_lru(&'a', &4, 2, square), 16); // evicts 3
+assert_eq!(storage.cache_lru(&'a', &2, 2, square), 4);
+assert_eq!(call_count.get(), 3);
 
-See [`" stringify!($shared) "::cache_with`] for a lower-level version which does not require
-`Output: Clone`.
-"=>
-    pub fn cache<Key, Scope, Arg, Input, Output>(
+assert_eq!(storage.cache_lru(&'a', &3, 2, square), 9);
+assert_eq!(call_count.get(), 4, "3 was least recently used");
+```
+
+# Panics
+
+If `capacity` is zero.
+"#=>
+    pub fn cache_lru<Key, Scope, Arg, Input, Output>(
         &self,
         key: &Key,
         arg: &Arg,
+        capacity: usize,
         init: impl FnOnce(&Input) -> Output,
     ) -> Output
     where
         Key: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
         Scope: 'static + Borrow<Key> + Eq + Hash $(+ $bound)?,
         Arg: PartialEq<Input> + ToOwned<Owned=Input> + ?Sized,
-        Input: 'static + Borrow<Arg> $(+ $bound)?,
+        Input: 'static $(+ $bound)?,
         Output: 'static + Clone $(+ $bound)?,
     {
-        self.cache_with(key, arg, init, Clone::clone)
+        self.cache_lru_with(key, arg, capacity, init, Clone::clone)
     }}
 
-doc_comment!{r"
-Caches the result of `init(arg)` once per `key`, re-running it when `arg` changes.
+doc_comment!{"
+Forwards to [`" stringify!($cache) "::gc`]. Collected values are
+[dropped outside the lock](" stringify!($shared) "#dropping-values).
+"=>
+    pub fn gc(&self) {
+        self.removing($cache::gc_removing);
+    }}
 
-Does not return any reference to the cached value. See [`" stringify!($shared) "::cache`]
-for similar functionality that returns a copy of `Output` or
-[`" stringify!($shared) "::cache_with`] which allows specifying other pre-return functions.
+doc_comment!{"
+Forwards to [`" stringify!($cache) "::on_evict`].
 "=>
-    pub fn hold<Key, Scope, Arg, Input, Output>(
+    pub fn on_evict<Scope, Input, Output>(
         &self,
-        key: &Key,
-        arg: &Arg,
-        init: impl FnOnce(&Input) -> Output,
-    )
+        listener: impl FnMut(Scope, Output) + 'static $(+ $bound)?,
+    ) where
+        Scope: Clone + 'static $(+ $bound)?,
+        Input: 'static $(+ $bound)?,
+        Output: 'static $(+ $bound)?,
+    {
+        self.inner.$acquire().on_evict::<Scope, Input, Output>(listener);
+    }}
+
+doc_comment!{"
+Forwards to [`" stringify!($cache) "::persist`].
+"=>
+    #[cfg(feature = "serde")]
+    pub fn persist<Scope, Input, Output>(&self, name: impl Into<String>)
     where
-        Key: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+        Scope: 'static + Clone + Eq + Hash + Serialize + DeserializeOwned $(+ $bound)?,
+        Input: 'static + Clone + Serialize + DeserializeOwned $(+ $bound)?,
+        Output: 'static + Clone + Serialize + DeserializeOwned $(+ $bound)?,
+    {
+        self.inner.$acquire().persist::<Scope, Input, Output>(name);
+    }}
+
+doc_comment!{"
+Forwards to [`" stringify!($cache) "::save`]. The values are copied while the cache is locked and
+written to `serializer` after releasing the lock, so it may access the cache.
+"=>
+    #[cfg(feature = "serde")]
+    pub fn save<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let saved = { self.inner.$acquire().saved() };
+        saved.serialize(serializer)
+    }}
+
+doc_comment!{"
+Forwards to [`" stringify!($cache) "::load`]. Values are read from `deserializer` before locking
+the cache, and values evicted to stay within its capacity are
+[dropped outside the lock](" stringify!($shared) "#dropping-values).
+"=>
+    #[cfg(feature = "serde")]
+    pub fn load<'de, D: Deserializer<'de>>(&self, deserializer: D) -> Result<(), D::Error> {
+        let persisters = { self.inner.$acquire().persisters() };
+        let parsed = $cache::parse_saved(&persisters, deserializer)?;
+        self.removing(|cache, removed| cache.load_removing(parsed, removed));
+        Ok(())
+    }}
+
+doc_comment!{"
+Forwards to [`" stringify!($cache) "::stats`].
+"=>
+    pub fn stats(&self) -> CacheStats {
+        self.inner.$acquire().stats()
+    }}
+
+doc_comment!{"
+Forwards to [`" stringify!($cache) "::visit`]. The cache is locked while it's visited, so
+`visitor` must not access it.
+"=>
+    pub fn visit(&self, visitor: &mut impl CacheVisitor) {
+        self.inner.$acquire().visit(visitor);
+    }}
+
+doc_comment!{"
+Forwards to [`" stringify!($cache) "::invalidate`]. Invalidated values are
+[dropped outside the lock](" stringify!($shared) "#dropping-values).
+"=>
+    pub fn invalidate<Scope, Input, Output, Key>(&self, key: &Key) -> bool
+    where
+        Key: Eq + Hash + ?Sized,
         Scope: 'static + Borrow<Key> + Eq + Hash $(+ $bound)?,
-        Arg: PartialEq<Input> + ToOwned<Owned=Input> + ?Sized,
-        Input: 'static + Borrow<Arg> $(+ $bound)?,
+        Input: 'static $(+ $bound)?,
         Output: 'static $(+ $bound)?,
     {
-        self.cache_with(key, arg, init, |_| {})
+        self.removing(|cache, removed| {
+            cache.remove::<Scope, Input, Output, Key>(key, removed);
+            !removed.is_empty()
+        })
     }}
 
 doc_comment!{"
-Forwards to [`" stringify!($cache) "::gc`].
+Forwards to [`" stringify!($cache) "::invalidate_namespace`]. Invalidated values are
+[dropped outside the lock](" stringify!($shared) "#dropping-values).
 "=>
-    pub fn gc(&self) {
-        self.inner.$acquire().gc();
+    pub fn invalidate_namespace<Scope, Input, Output>(&self) -> usize
+    where
+        Scope: 'static,
+        Input: 'static,
+        Output: 'static,
+    {
+        self.removing(|cache, removed| {
+            cache.remove_namespace::<Scope, Input, Output>(removed);
+            removed.len()
+        })
+    }}
+
+doc_comment!{"
+Forwards to [`" stringify!($cache) "::invalidate_where`]. Invalidated values are
+[dropped outside the lock](" stringify!($shared) "#dropping-values). `matches` is called while the cache is locked, so it
+must not access the cache.
+"=>
+    pub fn invalidate_where<Scope>(&self, mut matches: impl FnMut(&Scope) -> bool) -> usize
+    where
+        Scope: 'static,
+    {
+        self.removing(|cache, removed| {
+            cache.remove_where(&mut matches, removed);
+            removed.len()
+        })
     }}
 
+doc_comment!{"
+Forwards to [`" stringify!($cache) "::clear`]. Cleared values are
+[dropped outside the lock](" stringify!($shared) "#dropping-values).
+"=>
+    pub fn clear(&self) {
+        self.removing($cache::clear_removing);
+    }}
+
+    /// Run `op` with the cache locked, then notify listeners of any values it removed after
+    /// releasing the lock.
+    fn removing<R>(&self, op: impl FnOnce(&mut $cache, &mut Removed) -> R) -> R {
+        let (ret, evictions) = {
+            let mut inner = self.inner.$acquire();
+            let mut removed = Removed::default();
+            let ret = op(&mut inner, &mut removed);
+            (ret, inner.listen(removed))
+        };
+        evictions.notify();
+        ret
+    }
+
     fn addr(&self) -> usize {
         $refct::as_ptr(&self.inner) as *const _ as _
     }
@@ -551,6 +1693,550 @@ mod $test_mod {
         assert_counts!(1, 1); // prior GC had no accesses, should be dropped
     }
 
+    #[test]
+    fn clear_drops_dependents_first() {
+        let storage = $shared::default();
+        let dropped = Arc::new(Mutex::new(Vec::new()));
+
+        let guard = |name: &'static str| {
+            let dropped = dropped.clone();
+            scopeguard::guard(name, move |name| dropped.lock().push(name))
+        };
+
+        storage.hold(&'a', &(), |()| {
+            storage.hold(&'b', &(), |()| {
+                storage.hold(&'c', &(), |()| guard("c"));
+                guard("b")
+            });
+            guard("a")
+        });
+        storage.hold(&'d', &(), |()| guard("d"));
+
+        storage.clear();
+        let dropped = dropped.lock();
+        assert_eq!(dropped.len(), 4, "all values must be dropped");
+        let position = |name| dropped.iter().position(|d| *d == name).unwrap();
+        assert!(position("a") < position("b"), "a depends on b");
+        assert!(position("b") < position("c"), "b depends on c");
+    }
+
+    #[test]
+    fn clear_keeps_registrations() {
+        let mut cache = $cache::with_capacity(10);
+        cache.set_weigher(|s: &String| s.len());
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let evicted2 = evicted.clone();
+        cache.on_evict::<char, (), String>(move |scope, _| evicted2.lock().push(scope));
+        let storage = $shared::from(cache);
+
+        storage.hold(&'a', &(), |()| String::from("hello"));
+        storage.clear();
+        assert_eq!(*evicted.lock(), ['a']);
+
+        storage.hold(&'b', &(), |()| String::from("world"));
+        assert_eq!(storage.stats().weight(), 5, "the weigher must still be registered");
+        storage.clear();
+        assert_eq!(*evicted.lock(), ['a', 'b'], "the listener must still be registered");
+    }
+
+    #[test]
+    fn lru_outputs_collected_together() {
+        let storage = $shared::default();
+        let drop_count = Arc::new(AtomicU32::new(0));
+        let make_guard = |&n: &u32| {
+            let drop_count = drop_count.clone();
+            scopeguard::guard(n, move |_| {
+                drop_count.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        let get = |n| storage.cache_lru_with(&'a', &n, 2, make_guard, |g| **g);
+
+        assert_eq!(get(1), 1);
+        assert_eq!(get(2), 2);
+        assert_eq!(get(3), 3);
+        assert_eq!(drop_count.load(Ordering::SeqCst), 1, "1 evicted by 3");
+
+        storage.gc();
+        assert_eq!(get(2), 2, "reading one output retains all of them");
+        storage.gc();
+        assert_eq!(drop_count.load(Ordering::SeqCst), 1);
+
+        storage.gc();
+        assert_eq!(drop_count.load(Ordering::SeqCst), 3, "unused outputs dropped by gc");
+    }
+
+    #[test]
+    fn evicts_dead_values_before_least_recently_used() {
+        let mut cache = $cache::with_capacity(3);
+        cache.set_weigher(|n: &usize| *n);
+        let storage = $shared::from(cache);
+        let get = |scope: char, weight: usize| storage.cache(&scope, &weight, |&w| w);
+
+        struct Scopes(Vec<char>);
+        impl CacheVisitor for Scopes {
+            fn visit_entry(&mut self, entry: VisitedEntry<'_>) {
+                self.0.extend(entry.scope::<char>());
+            }
+        }
+        let stored = || {
+            let mut scopes = Scopes(Vec::new());
+            storage.visit(&mut scopes);
+            scopes.0.sort_unstable();
+            scopes.0
+        };
+
+        get('a', 1);
+        get('b', 1);
+        storage.gc();
+        get('c', 1);
+        get('b', 1);
+        assert_eq!(storage.stats().weight(), 3);
+
+        get('d', 1);
+        assert_eq!(stored(), ['b', 'c', 'd'], "'a' wasn't used since the last gc");
+
+        get('e', 1);
+        assert_eq!(stored(), ['b', 'd', 'e'], "'c' was the least recently used");
+
+        get('f', 5);
+        assert_eq!(stored(), ['f'], "the value just stored is kept even if it's too heavy");
+
+        let stats = storage.stats();
+        assert_eq!(stats.capacity(), Some(3));
+        assert_eq!(stats.weight(), 5);
+        assert_eq!(stats.evicted(), 5);
+    }
+
+    #[test]
+    fn nested_stores_under_capacity() {
+        let storage = $shared::from($cache::with_capacity(1));
+        storage.cache(&'x', &1u32, |&n| n);
+
+        let outer = storage.cache(&'x', &2u32, |&n| {
+            // storing 'y' puts the cache over capacity while 'x' is being re-initialized
+            storage.cache(&'y', &0u32, |&n| n);
+            n
+        });
+        assert_eq!(outer, 2);
+        let stats = storage.stats();
+        assert_eq!(stats.evicted(), 1, "'x' isn't evicted while it's being re-initialized");
+        assert_eq!(stats.weight(), 1, "'y' was evicted once 'x' was stored");
+        assert_eq!(storage.cache(&'x', &2u32, |_| -> u32 { unreachable!() }), 2);
+
+        storage.cache_lru(&'z', &1u32, 2, |&n| n);
+        let outer = storage.cache_lru(&'z', &2u32, 2, |&n| {
+            storage.cache(&'y', &0u32, |&n| n);
+            n
+        });
+        assert_eq!(outer, 2);
+        assert_eq!(storage.cache_lru(&'z', &2u32, 2, |_| -> u32 { unreachable!() }), 2);
+    }
+
+    #[test]
+    fn invalidated_during_init() {
+        let storage = $shared::default();
+        storage.cache(&'x', &1u32, |&n| n);
+
+        let outer = storage.cache(&'x', &2u32, |&n| {
+            assert!(storage.invalidate::<char, u32, u32, _>(&'x'));
+            n
+        });
+        assert_eq!(outer, 2);
+        let stored = || storage.cache(&'x', &2u32, |_| -> u32 { unreachable!() });
+        assert_eq!(stored(), 2, "stored to a fresh cell");
+        storage.gc();
+        assert_eq!(stored(), 2, "which was marked live");
+    }
+
+    #[test]
+    fn invalidated_values_dropped_outside_lock() {
+        /// Accesses the cache when dropped, which would panic or deadlock under its lock.
+        struct Reenters($shared);
+        impl Drop for Reenters {
+            fn drop(&mut self) {
+                self.0.hold(&'z', &(), |()| ());
+            }
+        }
+
+        let storage = $shared::default();
+        let make_guard = |_: &u32| Reenters(storage.clone());
+        storage.hold(&'a', &1, make_guard);
+        storage.cache_lru_with(&'a', &2, 2, make_guard, |_| ());
+        storage.hold(&'b', &1, make_guard);
+        storage.hold(&'c', &1, make_guard);
+        storage.hold(&'a', "unrelated", |s: &String| s.len());
+
+        assert!(storage.invalidate::<char, u32, Reenters, _>(&'a'), "both 'a' values dropped");
+        assert!(!storage.invalidate::<char, u32, Reenters, _>(&'a'));
+        assert_eq!(storage.stats().len(), 4, "'b', 'c', 'z' and the unrelated 'a' remain");
+
+        assert_eq!(storage.invalidate_where(|&c: &char| c == 'b'), 1);
+        assert_eq!(storage.invalidate_namespace::<char, u32, Reenters>(), 1);
+        assert_eq!(storage.stats().len(), 2);
+    }
+
+    #[test]
+    fn inputs_dropped_outside_lock() {
+        /// Accesses the cache when dropped, which would panic or deadlock under its lock.
+        #[derive(Clone, PartialEq)]
+        struct Reenters($shared);
+        impl Drop for Reenters {
+            fn drop(&mut self) {
+                self.0.hold(&'z', &(), |()| ());
+            }
+        }
+
+        let storage = $shared::default();
+        storage.hold(&'a', &Reenters(storage.clone()), |_| ());
+        assert!(storage.invalidate::<char, Reenters, (), _>(&'a'));
+        assert_eq!(storage.stats().len(), 1, "only 'z' remains");
+    }
+
+    #[test]
+    fn lru_outputs_passed_to_listeners() {
+        let storage = $shared::default();
+        let square = |&n: &u32| n * n;
+        storage.cache_lru(&'a', &1, 2, square);
+
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let evicted2 = evicted.clone();
+        storage.on_evict::<char, u32, u32>(move |scope, output| {
+            evicted2.lock().push((scope, output));
+        });
+        let take_evicted = || {
+            let mut evicted = std::mem::take(&mut *evicted.lock());
+            evicted.sort_unstable();
+            evicted
+        };
+
+        storage.cache_lru(&'a', &2, 2, square);
+        assert_eq!(take_evicted(), []);
+        storage.cache_lru(&'a', &3, 2, square);
+        assert_eq!(take_evicted(), [('a', 1)], "1 is no longer among the most recently used");
+
+        assert!(storage.invalidate::<char, u32, u32, _>(&'a'));
+        assert_eq!(take_evicted(), [('a', 4), ('a', 9)], "each output of an invalidated scope");
+
+        storage.cache_lru(&'b', &1, 2, square);
+        storage.gc();
+        storage.gc();
+        assert_eq!(take_evicted(), [('b', 1)], "collected by gc");
+    }
+
+    #[test]
+    fn listeners_notified_outside_lock() {
+        let mut cache = $cache::with_capacity(2);
+        cache.set_weigher(|n: &u32| *n as usize);
+        let storage = $shared::from(cache);
+
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let (storage2, evicted2) = (storage.clone(), evicted.clone());
+        storage.on_evict::<char, (), u32>(move |scope, output| {
+            // listeners can access the cache
+            assert!(storage2.stats().len() <= 2);
+            evicted2.lock().push((scope, output));
+        });
+        let take_evicted = || std::mem::take(&mut *evicted.lock());
+
+        storage.cache(&'a', &(), |()| 1u32);
+        storage.cache(&'b', &(), |()| 1u32);
+        assert_eq!(take_evicted(), []);
+
+        storage.cache(&'c', &(), |()| 1u32);
+        assert_eq!(take_evicted(), [('a', 1)], "evicted over capacity");
+
+        assert!(storage.invalidate::<char, (), u32, _>(&'b'));
+        assert_eq!(take_evicted(), [('b', 1)]);
+
+        storage.gc();
+        storage.gc();
+        assert_eq!(take_evicted(), [('c', 1)]);
+
+        storage.cache(&'d', &(), |()| 1u32);
+        storage.clear();
+        assert_eq!(take_evicted(), [('d', 1)]);
+
+        storage.cache(&'e', &(), |()| 1u32);
+        storage.on_evict::<char, (), u32>(|_, _| ());
+        storage.clear();
+        assert_eq!(take_evicted(), [], "listener was replaced");
+    }
+
+    #[test]
+    fn removals_made_while_notifying_are_queued() {
+        let storage = $shared::default();
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let (storage2, evicted2) = (storage.clone(), evicted.clone());
+        storage.on_evict::<char, (), u32>(move |scope, output| {
+            evicted2.lock().push((scope, output));
+            if scope == 'a' {
+                // removes a value of the listener's own query type
+                assert!(storage2.invalidate::<char, (), u32, _>(&'b'));
+                assert_eq!(evicted2.lock().len(), 1, "passed to the listener after it returns");
+            }
+        });
+
+        storage.hold(&'a', &(), |()| 1u32);
+        storage.hold(&'b', &(), |()| 2u32);
+        assert!(storage.invalidate::<char, (), u32, _>(&'a'));
+        assert_eq!(std::mem::take(&mut *evicted.lock()), [('a', 1), ('b', 2)]);
+
+        /// Invalidates another value of its own query type when dropped.
+        struct Invalidates(Option<$shared>, char);
+        impl Drop for Invalidates {
+            fn drop(&mut self) {
+                if let Some(storage) = self.0.take() {
+                    storage.invalidate::<char, (), Invalidates, _>(&self.1);
+                }
+            }
+        }
+
+        let evicted2 = evicted.clone();
+        storage.on_evict::<char, (), Invalidates>(move |scope, output| {
+            drop(output);
+            evicted2.lock().push((scope, 0));
+        });
+
+        storage.hold(&'c', &(), |()| Invalidates(Some(storage.clone()), 'd'));
+        storage.hold(&'d', &(), |()| Invalidates(None, 'd'));
+        assert!(storage.invalidate::<char, (), Invalidates, _>(&'c'));
+        assert_eq!(std::mem::take(&mut *evicted.lock()), [('c', 0), ('d', 0)]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn load_keeps_stored_values_and_skips_unregistered_queries() {
+        let storage = $shared::default();
+        storage.persist::<char, (), u32>("chars");
+        storage.persist::<u8, (), String>("bytes");
+        storage.hold(&'a', &(), |()| 1u32);
+        storage.hold(&'b', &(), |()| 2u32);
+        storage.hold(&1u8, &(), |()| String::from("one"));
+        let mut saved = Vec::new();
+        storage.save(&mut serde_json::Serializer::new(&mut saved)).unwrap();
+
+        let restored = $shared::default();
+        restored.persist::<char, (), u32>("chars");
+        restored.hold(&'a', &(), |()| 10u32);
+        restored.load(&mut serde_json::Deserializer::from_slice(&saved)).unwrap();
+        assert_eq!(restored.stats().len(), 2, "values of unregistered queries are ignored");
+        assert_eq!(restored.cache(&'a', &(), |()| 0u32), 10, "stored values aren't replaced");
+        assert_eq!(restored.cache(&'b', &(), |()| 0u32), 2);
+
+        let mismatched = $shared::default();
+        mismatched.persist::<char, (), u32>("chars");
+        mismatched.persist::<u8, (), String>("bytes");
+        let corrupted = String::from_utf8(saved).unwrap().replace("\"one\"", "1");
+        assert!(mismatched.load(&mut serde_json::Deserializer::from_str(&corrupted)).is_err());
+        assert_eq!(mismatched.stats().len(), 0, "nothing loaded if any query fails to parse");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn save_and_load_non_self_describing_formats() {
+        let storage = $shared::default();
+        storage.persist::<char, u32, String>("numbers");
+        storage.persist::<u8, (), Vec<u8>>("bytes");
+        storage.hold(&'a', &1u32, |n| n.to_string());
+        storage.hold(&2u8, &(), |()| vec![2u8, 3]);
+
+        let options = bincode::DefaultOptions::new();
+        let mut saved = Vec::new();
+        storage.save(&mut bincode::Serializer::new(&mut saved, options)).unwrap();
+
+        let restored = $shared::default();
+        restored.persist::<char, u32, String>("numbers");
+        restored.persist::<u8, (), Vec<u8>>("bytes");
+        restored.load(&mut bincode::Deserializer::from_slice(&saved, options)).unwrap();
+        assert_eq!(restored.cache(&'a', &1u32, |_| String::new()), "1");
+        assert_eq!(restored.cache(&2u8, &(), |()| Vec::<u8>::new()), [2, 3]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn saved_values_written_and_read_outside_lock() {
+        /// Accesses the cache while values are written to or read from it, which would panic or
+        /// deadlock under its lock.
+        struct Reenters<T>($shared, T);
+        impl std::io::Write for Reenters<Vec<u8>> {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.hold(&'z', &(), |()| ());
+                self.1.write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        impl std::io::Read for Reenters<&[u8]> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.0.hold(&'z', &(), |()| ());
+                self.1.read(buf)
+            }
+        }
+
+        let storage = $shared::default();
+        storage.persist::<char, (), u32>("chars");
+        storage.hold(&'a', &(), |()| 1u32);
+        let mut writer = Reenters(storage.clone(), Vec::new());
+        storage.save(&mut serde_json::Serializer::new(&mut writer)).unwrap();
+
+        let restored = $shared::default();
+        restored.persist::<char, (), u32>("chars");
+        let reader = Reenters(restored.clone(), &writer.1[..]);
+        restored.load(&mut serde_json::Deserializer::from_reader(reader)).unwrap();
+        assert_eq!(restored.cache(&'a', &(), |()| 0u32), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    #[should_panic(expected = "another query type is already persisted as \"chars\"")]
+    fn persisted_names_are_unique() {
+        let storage = $shared::default();
+        storage.persist::<char, (), u32>("chars");
+        storage.persist::<char, (), u32>("chars");
+        storage.persist::<char, (), u8>("chars");
+    }
+
+    #[test]
+    fn stats_track_gc_outcomes() {
+        let storage = $shared::default();
+        assert!(storage.stats().is_empty());
+
+        for i in 0..4 {
+            storage.hold(&i, &(), |_| ());
+        }
+        storage.hold("other", "namespace", |_| ());
+        storage.gc();
+        for i in 0..3 {
+            storage.hold(&i, &(), |_| ());
+        }
+        storage.gc();
+
+        let stats = storage.stats();
+        assert_eq!(stats.revision(), 2);
+        assert_eq!(stats.len(), 3);
+        let strings = &stats.namespaces()[0];
+        assert_eq!((strings.scope_type(), strings.len()), ("alloc::string::String", 0));
+        assert_eq!((strings.live_at_last_gc(), strings.dropped_at_last_gc()), (0, 1));
+        let ints = &stats.namespaces()[1];
+        assert_eq!((ints.scope_type(), ints.len()), ("i32", 3));
+        assert_eq!((ints.live_at_last_gc(), ints.dropped_at_last_gc()), (3, 1));
+
+        struct Scopes(Vec<(i32, bool)>);
+        impl CacheVisitor for Scopes {
+            fn visit_entry(&mut self, entry: VisitedEntry<'_>) {
+                if let Some(scope) = entry.scope::<i32>() {
+                    self.0.push((*scope, entry.is_live()));
+                }
+            }
+        }
+
+        storage.hold(&1, &(), |_| ());
+        let mut scopes = Scopes(Vec::new());
+        storage.visit(&mut scopes);
+        scopes.0.sort_unstable();
+        assert_eq!(scopes.0, [(0, false), (1, true), (2, false)]);
+    }
+
+    #[test]
+    fn debug_scopes_registered_for_existing_and_new_namespaces() {
+        let mut cache = $cache::default();
+        let (entry, ()) = cache.get(&1u8, &()).unwrap_err().init(|_| ((), ()));
+        cache.store(entry);
+        cache.debug_scopes::<u8>();
+        let (entry, ()) = cache.get(&2u8, "input").unwrap_err().init(|_| ((), ()));
+        cache.store(entry);
+
+        let output = format!("{:?}", cache);
+        assert!(output.contains("\"input\": \"()\", \"output\": \"()\", \"scopes\": [1]"));
+        assert!(output.contains("\"scopes\": [2]"), "namespaces made later are formatted too");
+
+        struct Scopes(Vec<String>);
+        impl CacheVisitor for Scopes {
+            fn visit_entry(&mut self, entry: VisitedEntry<'_>) {
+                self.0.push(format!("{:?}", entry));
+            }
+        }
+        let mut scopes = Scopes(Vec::new());
+        cache.visit(&mut scopes);
+        scopes.0.sort_unstable();
+        assert_eq!(
+            scopes.0,
+            [
+                "VisitedEntry { scope: 1, live: true }",
+                "VisitedEntry { scope: 2, live: true }",
+            ]
+        );
+    }
+
+    #[test]
+    fn self_referential_query_collected_when_unused() {
+        let storage = $shared::default();
+        let call_count = std::cell::Cell::new(0);
+        let count = |_: &i32| call_count.set(call_count.get() + 1);
+
+        storage.hold(&'a', &1, count);
+        // re-initializing 'a' reads its previous value, making 'a' one of its own dependents
+        storage.hold(&'a', &2, |_| {
+            storage.hold(&'a', &1, |_| unreachable!("the previous value is stored"));
+            count(&2);
+        });
+        assert_eq!(call_count.get(), 2);
+
+        for _ in 0..3 {
+            storage.gc();
+            storage.hold(&'a', &2, count);
+            assert_eq!(call_count.get(), 2, "reading 'a' retains it");
+        }
+
+        storage.gc();
+        storage.gc();
+        storage.hold(&'a', &2, count);
+        assert_eq!(call_count.get(), 3, "'a' is collected once it's unused");
+    }
+
+    #[test]
+    fn mutually_recursive_queries_collected_together() {
+        let storage = $shared::default();
+        let inits = Arc::new(Mutex::new(Vec::new()));
+        let init = |name: &'static str| {
+            inits.lock().push(name);
+            name
+        };
+
+        // 'a' depends on 'b'...
+        storage.cache(&'a', &1, |_| {
+            storage.cache(&'b', &1, |_| init("b1"));
+            init("a1")
+        });
+        storage.gc();
+
+        // ...and re-initializing 'b' makes it depend on 'a'
+        storage.cache(&'b', &2, |_| {
+            storage.cache(&'a', &1, |_| init("unreachable"));
+            init("b2")
+        });
+
+        for _ in 0..3 {
+            storage.gc();
+            storage.cache(&'a', &1, |_| init("a again"));
+        }
+        storage.gc();
+        storage.cache(&'b', &2, |_| init("b again"));
+        assert_eq!(*inits.lock(), ["b1", "a1", "b2"], "reading 'a' retains 'b' through the cycle");
+
+        // the read of 'b' above retains 'a' for one more gc
+        storage.gc();
+        storage.gc();
+        storage.cache(&'a', &1, |_| init("a3"));
+        storage.cache(&'b', &2, |_| init("b3"));
+        assert_eq!(
+            *inits.lock(),
+            ["b1", "a1", "b2", "a3", "b3"],
+            "values in a cycle are collected once none of them are read",
+        );
+    }
+
     struct CountDrops {
         num_drops: Arc<AtomicU32>,
     }
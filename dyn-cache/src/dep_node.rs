@@ -3,6 +3,7 @@ use illicit::AsContext;
 use parking_lot::Mutex;
 use std::{
     cmp::Ordering,
+    collections::HashMap,
     hash::{Hash, Hasher},
     sync::{Arc, Weak},
 };
@@ -36,40 +37,116 @@ impl DepNode {
         Dependent { inner: Arc::downgrade(&self.inner) }
     }
 
+    /// Returns true if this node was marked live during the current GC.
+    ///
+    /// Blocks while another thread holds this node's lock, but no thread holds a node's lock while
+    /// acquiring any other lock, so this can't deadlock with a concurrent GC.
     pub fn is_known_live(&self) -> bool {
-        // TODO(#174) find a better way to handle cycles
-        if let Some(l) = self.inner.try_lock() {
-            matches!(l.liveness, Liveness::Live)
-        } else {
-            false
-        }
+        matches!(self.inner.lock().liveness, Liveness::Live)
     }
 
-    fn should_inherit_liveness(&self, current_revision: u64) -> bool {
-        if let Some(l) = self.inner.try_lock() {
-            // if the dependent was updated during this revision, then our dependency should only
-            // consider *its own* liveness. consider the following pseudocode:
-            //
-            //     cache.cache_with(unique_value(), |_| {
-            //         if externally_modifiable_bool() {
-            //              cache.hold_with((), |v| op(v));
-            //         }
-            //     });
-            //
-            // in this case, the inner hold_with() call should not be retained if
-            // externally_modifiable_bool() returns false. to achieve this, we want the
-            // cache_with call's liveness to never propagate when the initialization closure
-            // executes.
-            l.updated_at_revision != current_revision
-        } else {
-            false
-        }
+    /// Returns true if any of this node's dependents are still stored.
+    pub fn has_dependents(&self) -> bool {
+        let dependents: Vec<_> =
+            self.inner.lock().dependents.iter().filter_map(Dependent::upgrade).collect();
+        // a node may depend on itself, so only lock dependents once we've released our own lock
+        dependents.iter().any(|d| !d.inner.lock().removed)
     }
 
-    pub fn update_liveness(&mut self, current_revision: u64) {
-        // TODO(#174) find a better way to handle cycles
-        if let Some(mut this) = self.inner.try_lock() {
-            this.update_liveness(current_revision);
+    /// Mark this node's value as removed from the cache. It may outlive the cache's lock so that
+    /// it can be dropped afterwards, but it no longer counts as a dependent of other nodes.
+    pub fn mark_removed(&self) {
+        self.inner.lock().removed = true;
+    }
+
+    /// Returns this node's dependents which are still stored, dropping the rest.
+    fn live_dependents(&self) -> Vec<DepNode> {
+        let mut inner = self.inner.lock();
+        inner.dependents.sort_unstable();
+        inner.dependents.dedup();
+
+        let mut upgraded = Vec::with_capacity(inner.dependents.len());
+        inner.dependents.retain(|dependent| {
+            if let Some(node) = dependent.upgrade() {
+                upgraded.push(node);
+                true
+            } else {
+                false
+            }
+        });
+        upgraded
+    }
+
+    /// Check this node's transitive dependents for roots, marking it live if one exists.
+    ///
+    /// Dependents are visited iteratively and only once each, locking one node at a time, so
+    /// values which depend on themselves (even indirectly) are handled like any others. The outcome
+    /// is shared with the other nodes visited: those on the path to a root are marked live too, and
+    /// if there's no root, every node visited is skipped by later checks in the same revision.
+    pub fn update_liveness(&self, current_revision: u64) {
+        {
+            let inner = self.inner.lock();
+            if matches!(inner.liveness, Liveness::Live)
+                || inner.dead_at_revision == Some(current_revision)
+            {
+                // we've already been here this gc, nothing new to see here
+                return;
+            }
+        }
+
+        // nodes are compared by address, see impl_common_traits_for_type_with_addr. each visited
+        // node is stored with the node whose dependents led to it
+        let mut visited = HashMap::new();
+        visited.insert(self.addr(), (self.clone(), None));
+        let mut to_visit: Vec<_> =
+            self.live_dependents().into_iter().map(|d| (d, self.clone())).collect();
+
+        while let Some((dependent, via)) = to_visit.pop() {
+            if visited.contains_key(&dependent.addr()) {
+                continue;
+            }
+
+            {
+                let inner = dependent.inner.lock();
+                // if the dependent was updated during this revision, then our dependency should
+                // only consider *its own* liveness. consider the following pseudocode:
+                //
+                //     cache.cache_with(unique_value(), |_| {
+                //         if externally_modifiable_bool() {
+                //              cache.hold_with((), |v| op(v));
+                //         }
+                //     });
+                //
+                // in this case, the inner hold_with() call should not be retained if
+                // externally_modifiable_bool() returns false. to achieve this, we want the
+                // cache_with call's liveness to never propagate when the initialization closure
+                // executes.
+                if inner.updated_at_revision == current_revision
+                    || inner.dead_at_revision == Some(current_revision)
+                {
+                    continue;
+                }
+
+                if matches!(inner.liveness, Liveness::Live) {
+                    // we found a transitive root, which retains each node on the way to it
+                    drop(inner);
+                    let mut on_path = Some(via);
+                    while let Some(node) = on_path {
+                        node.inner.lock().liveness = Liveness::Live;
+                        on_path = visited.remove(&node.addr()).and_then(|(_, via)| via);
+                    }
+                    return;
+                }
+            }
+
+            to_visit
+                .extend(dependent.live_dependents().into_iter().map(|d| (d, dependent.clone())));
+            visited.insert(dependent.addr(), (dependent, Some(via)));
+        }
+
+        // none of the nodes we visited can reach a root either
+        for (node, _) in visited.values() {
+            node.inner.lock().dead_at_revision = Some(current_revision);
         }
     }
 
@@ -89,12 +166,21 @@ impl_common_traits_for_type_with_addr!(DepNode);
 struct InnerDepNode {
     liveness: Liveness,
     updated_at_revision: u64,
+    /// the revision in which this node was found to have no transitive roots
+    dead_at_revision: Option<u64>,
     dependents: Vec<Dependent>,
+    removed: bool,
 }
 
 impl Default for InnerDepNode {
     fn default() -> Self {
-        Self { liveness: Liveness::Live, updated_at_revision: 0, dependents: Vec::new() }
+        Self {
+            liveness: Liveness::Live,
+            updated_at_revision: 0,
+            dead_at_revision: None,
+            dependents: Vec::new(),
+            removed: false,
+        }
     }
 }
 
@@ -110,39 +196,6 @@ impl InnerDepNode {
         self.updated_at_revision = revision;
     }
 
-    /// Check incoming dependents for roots, marking ourselves live if a root
-    /// exists. Drops stale dependents.
-    fn update_liveness(&mut self, current_revision: u64) {
-        self.dependents.sort_unstable();
-        self.dependents.dedup();
-
-        if matches!(self.liveness, Liveness::Live) {
-            // we've already been here this gc, nothing new to see here
-            return;
-        }
-
-        let mut has_root = false;
-        self.dependents.retain(|dependent| {
-            let mut keep = false;
-
-            if let Some(mut dependent) = dependent.upgrade() {
-                dependent.update_liveness(current_revision);
-                if dependent.should_inherit_liveness(current_revision) && dependent.is_known_live()
-                {
-                    has_root = true;
-                }
-                keep = true;
-            }
-
-            keep
-        });
-
-        // if we found a transitive root then mark ourselves live
-        if has_root {
-            self.liveness = Liveness::Live;
-        }
-    }
-
     fn mark_dead(&mut self) {
         self.liveness = Liveness::Dead;
     }
@@ -182,3 +235,71 @@ impl Dependent {
 }
 
 impl_common_traits_for_type_with_addr!(Dependent);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::SharedSendCache;
+    use std::thread;
+
+    /// Returns dead nodes where each is a dependent of the one before it.
+    fn dead_chain(len: usize) -> Vec<DepNode> {
+        let mut nodes = vec![DepNode::new(Dependent::default(), 0)];
+        for _ in 1..len {
+            let node = DepNode::new(nodes.last().unwrap().as_dependent(), 0);
+            nodes.push(node);
+        }
+        nodes.reverse();
+        nodes.iter_mut().for_each(DepNode::mark_dead);
+        nodes
+    }
+
+    #[test]
+    fn roots_retain_long_chains() {
+        let mut nodes = dead_chain(10_000);
+        nodes.last_mut().unwrap().root_read(Dependent::default());
+
+        for node in &nodes {
+            node.update_liveness(1);
+        }
+        assert!(nodes.iter().all(DepNode::is_known_live));
+    }
+
+    #[test]
+    fn long_dead_chains_checked_once() {
+        let nodes = dead_chain(10_000);
+
+        nodes[0].update_liveness(1);
+        assert!(nodes.iter().all(|n| n.inner.lock().dead_at_revision == Some(1)));
+
+        for node in &nodes {
+            node.update_liveness(1);
+        }
+        assert!(!nodes.iter().any(DepNode::is_known_live));
+    }
+
+    #[test]
+    fn concurrent_gc_and_reads() {
+        let storage = SharedSendCache::default();
+        let readers: Vec<_> = (0..4)
+            .map(|i| {
+                let storage = storage.clone();
+                thread::spawn(move || {
+                    for j in 0..1_000 {
+                        storage.cache(&i, &(j % 10), |&n| {
+                            storage.cache(&(i, n), &(), |()| n);
+                            // every thread's values depend on this one
+                            storage.cache(&"shared", &(), |()| 0);
+                            n
+                        });
+                    }
+                })
+            })
+            .collect();
+
+        while readers.iter().any(|r| !r.is_finished()) {
+            storage.gc();
+        }
+        readers.into_iter().for_each(|r| r.join().unwrap());
+    }
+}
@@ -328,7 +328,7 @@ use downcast_rs::{impl_downcast, Downcast};
 use hash_hasher::HashBuildHasher;
 use hashbrown::hash_map::DefaultHashBuilder;
 use std::{
-    any::TypeId,
+    any::{Any, TypeId},
     fmt::{Debug, Formatter, Result as FmtResult},
     hash::{BuildHasher, Hash, Hasher},
     marker::PhantomData,
@@ -339,9 +339,14 @@ mod definition;
 
 mod cache_cell;
 mod dep_node;
+mod lru;
 mod namespace;
+mod stats;
 
+use cache_cell::CacheCell;
 use namespace::{KeyMiss, Namespace};
+use stats::DebugScope;
+pub use stats::{CacheStats, CacheVisitor, NamespaceStats, VisitedEntry};
 
 /// The result of a failed attempt to retrieve a value from the cache.
 /// Initialize a full [`CacheEntry`] for storage with [`CacheMiss::init`].
@@ -434,12 +439,99 @@ trait Storage: Downcast + Debug {
     /// Traverse stored values, identifying roots.
     fn mark(&mut self, revision: u64);
 
-    /// Remove dead entries.
-    fn sweep(&mut self);
+    /// Remove dead entries, adding them to `removed`.
+    fn sweep(&mut self, removed: &mut Removed);
+
+    /// Remove entries which no other live entries depend on, adding them to `removed`.
+    fn drop_unreferenced(&mut self, removed: &mut Removed);
+
+    /// Remove entries whose scopes `matches`, adding them to `removed`.
+    fn remove_where(&mut self, matches: &mut dyn FnMut(&dyn Any) -> bool, removed: &mut Removed);
+
+    /// Returns the total weight of the stored values.
+    fn weight(&self) -> usize;
+
+    /// Add the eviction order and weight of each stored value to `candidates`.
+    /// Values which are being re-initialized by a query that missed during `revision` are skipped.
+    fn eviction_candidates(&self, revision: u64, candidates: &mut Vec<((bool, u64), usize)>);
+
+    /// Remove entries which come at or before `cutoff` in eviction order, adding them to `removed`.
+    /// Values which are being re-initialized by a query that missed during `revision` are kept.
+    fn evict(&mut self, cutoff: (bool, u64), revision: u64, removed: &mut Removed);
+
+    /// Summarize the stored values.
+    fn stats(&self) -> NamespaceStats;
+
+    /// Pass a summary of the stored values and each of their scopes to `visitor`.
+    fn visit(&self, visitor: &mut dyn CacheVisitor);
+
+    /// Format scopes with `debug` if they're of the type `scope_type`.
+    fn debug_scopes(&mut self, scope_type: TypeId, debug: DebugScope);
+
+    /// Add replaced values to `Removed` if this stores values of the query type `query`, so that
+    /// they're passed to its listener.
+    fn listen(&mut self, query: TypeId);
 }
 
 impl_downcast!(Storage);
 
+/// Values removed from a cache, collected so they can be passed to eviction listeners and dropped
+/// after releasing any lock on it.
+#[derive(Default)]
+struct Removed {
+    /// each boxed `Removal`, with the `TypeId` of its query
+    values: Vec<(TypeId, Box<dyn Any>)>,
+}
+
+impl Removed {
+    fn push<Scope, Input, Output>(&mut self, removal: Removal<Scope, Input, Output>)
+    where
+        Scope: 'static,
+        Input: 'static,
+        Output: 'static,
+    {
+        self.values.push((TypeId::of::<(Scope, Input, Output)>(), Box::new(removal)));
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// A value removed from the storage for a query type.
+enum Removal<Scope, Input, Output> {
+    /// A cell removed from the cache along with its scope.
+    Cell(Scope, CacheCell<Input, Output>),
+    /// An input and output replaced in a cell which is still stored.
+    Replaced(Scope, Input, Output),
+}
+
+impl<Scope, Input, Output> Removal<Scope, Input, Output> {
+    /// Returns the removal of `cell`, which no longer keeps the values it read from being dropped.
+    fn cell(scope: Scope, cell: CacheCell<Input, Output>) -> Self {
+        cell.mark_removed();
+        Removal::Cell(scope, cell)
+    }
+
+    /// Returns the scope and output to pass to a listener, dropping the rest.
+    fn into_scope_output(self) -> (Scope, Output) {
+        match self {
+            Removal::Cell(scope, cell) => (scope, cell.into_output()),
+            Removal::Replaced(scope, _, output) => (scope, output),
+        }
+    }
+}
+
+/// A type-erased eviction listener for a single query type.
+trait Notify: Debug {
+    /// Pass a boxed `Removal` to the listener.
+    fn notify(&self, removed: Box<dyn Any>);
+}
+
 /// Describes the outcome of garbage collection for a cached value.
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum Liveness {
@@ -474,8 +566,12 @@ where
         new
     }
 
-    fn make_namespace(&self) -> Box<Namespace<Scope, Input, Output>> {
-        Box::new(Namespace::default())
+    fn make_namespace(
+        &self,
+        debug_scope: Option<DebugScope>,
+        listened: bool,
+    ) -> Box<Namespace<Scope, Input, Output>> {
+        Box::new(Namespace::new(debug_scope, listened))
     }
 
     fn hash(&self) -> u64 {
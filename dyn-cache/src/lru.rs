@@ -0,0 +1,105 @@
+use std::{
+    any::type_name,
+    collections::VecDeque,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+
+/// The most recently used input/output pairs for a single scope, stored as the
+/// output of a cache cell by the `cache_lru` family of functions.
+#[derive(Clone)]
+pub(crate) struct Lru<Input, Output> {
+    /// most recently used first, with each output's weight
+    entries: VecDeque<(Input, Output, usize)>,
+}
+
+impl<Input, Output> Lru<Input, Output> {
+    pub fn new(input: Input, output: Output, weight: usize) -> Self {
+        let mut entries = VecDeque::with_capacity(1);
+        entries.push_front((input, output, weight));
+        Self { entries }
+    }
+
+    /// Make the output stored for an input equal to `arg` the most recently
+    /// used, returning false if there isn't one.
+    pub fn promote<Arg>(&mut self, arg: &Arg) -> bool
+    where
+        Arg: PartialEq<Input> + ?Sized,
+    {
+        match self.entries.iter().position(|(input, ..)| arg == input) {
+            Some(0) => true,
+            Some(index) => {
+                let entry = self.entries.remove(index).unwrap();
+                self.entries.push_front(entry);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the most recently used output.
+    pub fn most_recent(&self) -> &Output {
+        &self.entries[0].1
+    }
+
+    /// Store a new input/output as the most recently used, returning the least
+    /// recently used entries beyond `capacity` if there are any.
+    pub fn insert(
+        &mut self,
+        input: Input,
+        output: Output,
+        weight: usize,
+        capacity: usize,
+    ) -> Option<Self> {
+        self.entries.push_front((input, output, weight));
+        if self.entries.len() > capacity {
+            Some(Self { entries: self.entries.split_off(capacity) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the stored outputs, most recently used first.
+    pub fn into_outputs(self) -> impl Iterator<Item = Output> {
+        self.entries.into_iter().map(|(_, output, _)| output)
+    }
+
+    /// Returns the total weight of the stored outputs.
+    pub fn weight(&self) -> usize {
+        self.entries.iter().map(|(.., weight)| weight).sum()
+    }
+}
+
+impl<Input, Output> Debug for Lru<Input, Output>
+where
+    Input: 'static,
+    Output: 'static,
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Lru")
+            .field("input", &type_name::<Input>())
+            .field("output", &type_name::<Output>())
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut lru = Lru::new(1, "one", 1);
+        assert!(lru.insert(2, "two", 1, 2).is_none());
+        assert!(lru.promote(&1), "hit makes 1 most recent");
+
+        let evicted = lru.insert(3, "three", 5, 2).unwrap();
+        assert_eq!(evicted.into_outputs().collect::<Vec<_>>(), ["two"]);
+        assert_eq!(lru.weight(), 6);
+        assert!(!lru.promote(&2), "2 was least recently used");
+        assert!(lru.promote(&1));
+        assert_eq!(lru.most_recent(), &"one");
+        assert!(lru.promote(&3));
+        assert_eq!(lru.most_recent(), &"three");
+    }
+}
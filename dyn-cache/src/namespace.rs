@@ -1,7 +1,9 @@
 use super::{
     cache_cell::CacheCell,
     dep_node::{DepNode, Dependent},
-    Storage,
+    lru::Lru,
+    stats::{DebugScope, GcCounts, ScopeDebug},
+    CacheVisitor, NamespaceStats, Removal, Removed, Storage, VisitedEntry,
 };
 use hashbrown::{
     hash_map::{DefaultHashBuilder, RawEntryMut},
@@ -9,7 +11,7 @@ use hashbrown::{
 };
 
 use std::{
-    any::type_name,
+    any::{type_name, Any, TypeId},
     borrow::Borrow,
     fmt::{Debug, Formatter, Result as FmtResult},
     hash::{BuildHasher, Hash, Hasher},
@@ -57,6 +59,12 @@ where
     }
 }
 
+/// Returns a node for a cell whose previous one was removed while a query was
+/// re-initializing it, for example by an invalidation from within the query.
+fn vanished_node(revision: u64) -> DepNode {
+    DepNode::new(Dependent::incoming(), revision)
+}
+
 /// A query key that was hashed as part of an initial lookup and which can be
 /// used to store fresh values back to the cache.
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -83,6 +91,15 @@ where
 #[derive(Clone)]
 pub(crate) struct Namespace<Scope, Input, Output, H = DefaultHashBuilder> {
     inner: HashMap<Scope, CacheCell<Input, Output>, H>,
+    /// total weight of the stored outputs
+    weight: usize,
+    /// # of values evicted to keep the cache within its capacity
+    evicted: usize,
+    last_gc: GcCounts,
+    /// formats scopes in debug output, if registered for `Scope`
+    debug_scope: Option<DebugScope>,
+    /// whether a listener will receive replaced values
+    listened: bool,
 }
 
 impl<Scope, Input, Output, H> Default for Namespace<Scope, Input, Output, H>
@@ -90,7 +107,25 @@ where
     H: Default,
 {
     fn default() -> Self {
-        Self { inner: Default::default() }
+        Self {
+            inner: Default::default(),
+            weight: 0,
+            evicted: 0,
+            last_gc: Default::default(),
+            debug_scope: None,
+            listened: false,
+        }
+    }
+}
+
+impl<Scope, Input, Output, H> Namespace<Scope, Input, Output, H>
+where
+    H: Default,
+{
+    /// Returns an empty namespace which formats its scopes with `debug_scope`
+    /// and adds replaced values to `Removed` if they'll be `listened` to.
+    pub fn new(debug_scope: Option<DebugScope>, listened: bool) -> Self {
+        Self { debug_scope, listened, ..Default::default() }
     }
 }
 
@@ -138,6 +173,7 @@ where
         arg: &Arg,
         dependent: Dependent,
         revision: u64,
+        tick: Option<u64>,
     ) -> Result<&Output, KeyMiss<'k, Key, Input, H>>
     where
         Key: Eq + Hash + ?Sized,
@@ -147,7 +183,8 @@ where
     {
         let hashed = self.hashed(key);
         if let Some((_, cell)) = self.entry(&hashed) {
-            cell.get(arg, dependent).map_err(|d| KeyMiss::hashed(hashed, arg.to_owned(), None, d))
+            cell.get(arg, dependent, revision, tick)
+                .map_err(|d| KeyMiss::hashed(hashed, arg.to_owned(), None, d))
         } else {
             let node = DepNode::new(dependent, revision);
             let new_dep = node.as_dependent();
@@ -155,29 +192,197 @@ where
         }
     }
 
-    pub fn store<Key>(&mut self, miss: KeyMiss<'_, Key, Input, H>, output: Output, revision: u64)
+    pub fn store<Key>(
+        &mut self,
+        miss: KeyMiss<'_, Key, Input, H>,
+        output: Output,
+        weight: usize,
+        revision: u64,
+        tick: Option<u64>,
+        removed: &mut Removed,
+    ) where
+        Key: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+        Scope: Borrow<Key>,
+    {
+        let (dependent, listened) = (miss.dependent, self.listened);
+        let hashed = miss.inner.unwrap_or_else(|k| self.hashed(k));
+        let replaced_weight = match self.entry_mut(&hashed) {
+            RawEntryMut::Occupied(occ) => {
+                assert!(miss.node.is_none(), "mustn't create nodes that aren't used");
+                let cell = occ.into_mut();
+                let replaced_weight = cell.weight();
+                let (input, output) =
+                    cell.store(miss.input, output, weight, dependent, revision, tick);
+                if listened {
+                    removed.push(Removal::Replaced(hashed.key.to_owned(), input, output));
+                }
+                replaced_weight
+            }
+            RawEntryMut::Vacant(vac) => {
+                let node = miss.node.unwrap_or_else(|| vanished_node(revision));
+                let cell = CacheCell::new(miss.input, output, weight, node, tick);
+                vac.insert(hashed.key.to_owned(), cell);
+                0
+            }
+        };
+        self.weight = self.weight - replaced_weight + weight;
+    }
+}
+
+impl<Scope, Input, Output, H> Namespace<Scope, Input, Output, H>
+where
+    Scope: Eq + Hash + 'static,
+    Input: 'static,
+    Output: 'static,
+    H: BuildHasher,
+{
+    /// Remove the value stored for `key`, if any.
+    pub fn remove<Key>(&mut self, key: &Key, removed: &mut Removed) -> bool
     where
+        Key: Eq + Hash + ?Sized,
+        Scope: Borrow<Key>,
+    {
+        let hashed = self.hashed(key);
+        if let RawEntryMut::Occupied(occ) = self.entry_mut(&hashed) {
+            let (scope, cell) = occ.remove_entry();
+            self.weight -= cell.weight();
+            removed.push(Removal::cell(scope, cell));
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<Scope, Input, Output, H> Namespace<Scope, Input, Output, H>
+where
+    Scope: Eq + Hash + 'static,
+    Input: 'static,
+    Output: 'static,
+    H: BuildHasher,
+{
+    /// Returns the scope, input and output of each stored value.
+    pub fn entries(&self) -> impl Iterator<Item = (&Scope, &Input, &Output)> {
+        self.inner.iter().map(|(scope, cell)| (scope, cell.input(), cell.output()))
+    }
+
+    /// Store a value saved by another cache unless a value is already stored
+    /// for `scope`. Loaded values are dead until they're read.
+    pub fn load(
+        &mut self,
+        scope: Scope,
+        input: Input,
+        output: Output,
+        weight: usize,
+        revision: u64,
+        tick: Option<u64>,
+    ) {
+        if let hashbrown::hash_map::Entry::Vacant(vacant) = self.inner.entry(scope) {
+            vacant.insert(CacheCell::dead(input, output, weight, revision, tick));
+            self.weight += weight;
+        }
+    }
+}
+
+impl<Scope, Input, Output, H> Namespace<Scope, (), Lru<Input, Output>, H>
+where
+    Scope: Eq + Hash + 'static,
+    Input: 'static,
+    Output: 'static,
+    H: BuildHasher,
+{
+    /// Like [`Namespace::get`], but returns any of the outputs stored for `key`
+    /// whose input equals `arg`, making it the most recently used.
+    pub fn get_lru<'k, Key, Arg>(
+        &mut self,
+        key: &'k Key,
+        arg: &Arg,
+        dependent: Dependent,
+        revision: u64,
+        tick: Option<u64>,
+    ) -> Result<&Output, KeyMiss<'k, Key, Input, H>>
+    where
+        Key: Eq + Hash + ?Sized,
+        Scope: Borrow<Key>,
+        Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    {
+        let hashed = self.hashed(key);
+        if let RawEntryMut::Occupied(occ) = self.entry_mut(&hashed) {
+            let cell = occ.into_mut();
+            let (lru, dependent) = cell.get_mut(dependent, tick);
+            if lru.promote(arg) {
+                Ok(cell.output().most_recent())
+            } else {
+                cell.mark_pending(revision);
+                Err(KeyMiss::hashed(hashed, arg.to_owned(), None, dependent))
+            }
+        } else {
+            let node = DepNode::new(dependent, revision);
+            let new_dep = node.as_dependent();
+            Err(KeyMiss::hashed(hashed, arg.to_owned(), Some(node), new_dep))
+        }
+    }
+
+    /// Like [`Namespace::store`], but keeps up to `capacity` of the most
+    /// recently used outputs stored for the key.
+    #[allow(clippy::too_many_arguments)] // the same as store, plus the capacity
+    pub fn store_lru<Key>(
+        &mut self,
+        miss: KeyMiss<'_, Key, Input, H>,
+        output: Output,
+        weight: usize,
+        capacity: usize,
+        revision: u64,
+        tick: Option<u64>,
+        removed: &mut Removed,
+    ) where
         Key: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
         Scope: Borrow<Key>,
     {
-        let dependent = miss.dependent;
+        let (dependent, listened) = (miss.dependent, self.listened);
         let hashed = miss.inner.unwrap_or_else(|k| self.hashed(k));
-        match self.entry_mut(&hashed) {
+        let (replaced_weight, new_weight) = match self.entry_mut(&hashed) {
             RawEntryMut::Occupied(occ) => {
                 assert!(miss.node.is_none(), "mustn't create nodes that aren't used");
-                occ.into_mut().store(miss.input, output, dependent, revision);
+                let (lru, cell_weight) = occ.into_mut().update(dependent, revision, tick);
+                let truncated = lru.insert(miss.input, output, weight, capacity);
+                if let (true, Some(truncated)) = (listened, truncated) {
+                    removed.push(Removal::Replaced(hashed.key.to_owned(), (), truncated));
+                }
+                let replaced_weight = std::mem::replace(cell_weight, lru.weight());
+                (replaced_weight, *cell_weight)
             }
             RawEntryMut::Vacant(vac) => {
-                vac.insert(
-                    hashed.key.to_owned(),
-                    CacheCell::new(
-                        miss.input,
-                        output,
-                        miss.node.expect("if no cell present, we must have created a fresh node"),
-                    ),
-                );
+                let node = miss.node.unwrap_or_else(|| vanished_node(revision));
+                let lru = Lru::new(miss.input, output, weight);
+                vac.insert(hashed.key.to_owned(), CacheCell::new((), lru, weight, node, tick));
+                (0, weight)
             }
+        };
+        self.weight = self.weight - replaced_weight + new_weight;
+    }
+}
+
+impl<Scope, Input, Output, H> Namespace<Scope, Input, Output, H>
+where
+    Scope: 'static,
+    Input: 'static,
+    Output: 'static,
+{
+    /// Remove the cells for which `should_remove` returns true, returning how
+    /// many were removed.
+    fn remove_cells(
+        &mut self,
+        mut should_remove: impl FnMut(&Scope, &mut CacheCell<Input, Output>) -> bool,
+        removed: &mut Removed,
+    ) -> usize {
+        let before = removed.len();
+        for (scope, cell) in self.inner.drain_filter(|scope, cell| should_remove(scope, cell)) {
+            self.weight -= cell.weight();
+            removed.push(Removal::cell(scope, cell));
         }
+        removed.len() - before
     }
 }
 
@@ -192,24 +397,84 @@ where
         self.inner.values_mut().for_each(|c| c.update_liveness(revision));
     }
 
-    fn sweep(&mut self) {
-        self.inner.retain(|_, c| {
-            let keep = c.is_live();
-            c.mark_dead();
-            keep
-        });
+    fn sweep(&mut self, removed: &mut Removed) {
+        let dropped = self.remove_cells(
+            |_, c| {
+                let keep = c.is_live();
+                c.mark_dead();
+                !keep
+            },
+            removed,
+        );
+        self.last_gc = GcCounts { live: self.inner.len(), dropped };
+    }
+
+    fn drop_unreferenced(&mut self, removed: &mut Removed) {
+        self.remove_cells(|_, c| !c.is_referenced(), removed);
+    }
+
+    fn remove_where(&mut self, matches: &mut dyn FnMut(&dyn Any) -> bool, removed: &mut Removed) {
+        self.remove_cells(|scope, _| matches(scope), removed);
+    }
+
+    fn weight(&self) -> usize {
+        self.weight
+    }
+
+    fn eviction_candidates(&self, revision: u64, candidates: &mut Vec<((bool, u64), usize)>) {
+        let cells = self.inner.values().filter(|c| !c.is_pending(revision));
+        candidates.extend(cells.map(|c| (c.eviction_order(), c.weight())));
+    }
+
+    fn evict(&mut self, cutoff: (bool, u64), revision: u64, removed: &mut Removed) {
+        self.evicted += self
+            .remove_cells(|_, c| !c.is_pending(revision) && c.eviction_order() <= cutoff, removed);
+    }
+
+    fn stats(&self) -> NamespaceStats {
+        NamespaceStats::new::<Scope, Input, Output>(
+            self.inner.len(),
+            self.weight,
+            self.evicted,
+            self.last_gc,
+        )
+    }
+
+    fn visit(&self, visitor: &mut dyn CacheVisitor) {
+        visitor.visit_namespace(&self.stats());
+        for (scope, cell) in &self.inner {
+            visitor.visit_entry(VisitedEntry::new(scope, self.debug_scope, cell.is_live()));
+        }
+    }
+
+    fn debug_scopes(&mut self, scope_type: TypeId, debug: DebugScope) {
+        if scope_type == TypeId::of::<Scope>() {
+            self.debug_scope = Some(debug);
+        }
+    }
+
+    fn listen(&mut self, query: TypeId) {
+        if query == TypeId::of::<(Scope, Input, Output)>() {
+            self.listened = true;
+        }
     }
 }
 
-impl<Scope, Input, Output, H> Debug for Namespace<Scope, Input, Output, H> {
+impl<Scope, Input, Output, H> Debug for Namespace<Scope, Input, Output, H>
+where
+    Scope: 'static,
+{
     // someday specialization might save us from these lame debug impls?
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        // TODO(#176) better debug output somehow?
-        f.debug_map()
-            .entry(&"scope", &type_name::<Scope>())
+        let mut out = f.debug_map();
+        out.entry(&"scope", &type_name::<Scope>())
             .entry(&"input", &type_name::<Input>())
-            .entry(&"output", &type_name::<Output>())
-            .finish()
+            .entry(&"output", &type_name::<Output>());
+        if let Some(debug) = self.debug_scope {
+            let scopes = self.inner.keys().map(|scope| ScopeDebug::new(scope, debug));
+            out.entry(&"scopes", &scopes.collect::<Vec<_>>());
+        }
+        out.finish()
     }
 }
 
@@ -0,0 +1,317 @@
+use std::{
+    any::Any,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+
+/// A summary of the values held by a cache, returned by
+/// [`crate::local::LocalCache::stats`] and [`crate::sync::SendCache::stats`].
+///
+/// # Example
+///
+/// ```
+/// let storage = dyn_cache::local::SharedLocalCache::default();
+/// storage.hold(&'a', &1u8, |_| ());
+/// storage.hold(&'b', &1u8, |_| ());
+/// storage.hold("c", "input", |_| ());
+///
+/// let stats = storage.stats();
+/// assert_eq!(stats.len(), 3);
+/// assert_eq!(stats.namespaces().len(), 2);
+///
+/// let chars = stats.namespaces().iter().find(|ns| ns.scope_type() == "char").unwrap();
+/// assert_eq!(chars.len(), 2);
+/// assert_eq!(chars.input_type(), "u8");
+///
+/// storage.gc();
+/// storage.hold(&'a', &1u8, |_| ());
+/// storage.gc();
+///
+/// let chars = storage.stats().namespaces().iter().find(|ns| ns.len() == 1).cloned().unwrap();
+/// assert_eq!(chars.live_at_last_gc(), 1);
+/// assert_eq!(chars.dropped_at_last_gc(), 1);
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    revision: u64,
+    capacity: Option<usize>,
+    namespaces: Vec<NamespaceStats>,
+}
+
+impl CacheStats {
+    pub(crate) fn new(
+        revision: u64,
+        capacity: Option<usize>,
+        mut namespaces: Vec<NamespaceStats>,
+    ) -> Self {
+        namespaces.sort_unstable_by_key(|ns| (ns.scope_type, ns.input_type, ns.output_type));
+        Self { revision, capacity, namespaces }
+    }
+
+    /// Returns the number of times the cache has been GC'd.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Returns a summary of each query type in the cache, sorted by type name.
+    pub fn namespaces(&self) -> &[NamespaceStats] {
+        &self.namespaces
+    }
+
+    /// Returns the total number of values stored in the cache.
+    pub fn len(&self) -> usize {
+        self.namespaces.iter().map(NamespaceStats::len).sum()
+    }
+
+    /// Returns true if the cache holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the maximum total weight of the values in the cache, if it has
+    /// one.
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Returns the total weight of the values stored in the cache.
+    pub fn weight(&self) -> usize {
+        self.namespaces.iter().map(NamespaceStats::weight).sum()
+    }
+
+    /// Returns the number of values which have been evicted to keep the cache
+    /// within its capacity.
+    pub fn evicted(&self) -> usize {
+        self.namespaces.iter().map(NamespaceStats::evicted).sum()
+    }
+}
+
+/// A summary of the values stored for a single query type in a cache.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NamespaceStats {
+    scope_type: &'static str,
+    input_type: &'static str,
+    output_type: &'static str,
+    len: usize,
+    weight: usize,
+    evicted: usize,
+    last_gc: GcCounts,
+}
+
+impl NamespaceStats {
+    pub(crate) fn new<Scope, Input, Output>(
+        len: usize,
+        weight: usize,
+        evicted: usize,
+        last_gc: GcCounts,
+    ) -> Self {
+        Self {
+            scope_type: std::any::type_name::<Scope>(),
+            input_type: std::any::type_name::<Input>(),
+            output_type: std::any::type_name::<Output>(),
+            len,
+            weight,
+            evicted,
+            last_gc,
+        }
+    }
+
+    /// Returns the name of the type which indexes the query's values.
+    pub fn scope_type(&self) -> &'static str {
+        self.scope_type
+    }
+
+    /// Returns the name of the query's input type.
+    pub fn input_type(&self) -> &'static str {
+        self.input_type
+    }
+
+    /// Returns the name of the query's output type.
+    pub fn output_type(&self) -> &'static str {
+        self.output_type
+    }
+
+    /// Returns the number of values currently stored for the query.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no values are stored for the query.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the total weight of the values currently stored for the query.
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    /// Returns the number of the query's values which have been evicted to
+    /// keep the cache within its capacity.
+    pub fn evicted(&self) -> usize {
+        self.evicted
+    }
+
+    /// Returns the number of values which were retained by the most recent GC.
+    pub fn live_at_last_gc(&self) -> usize {
+        self.last_gc.live
+    }
+
+    /// Returns the number of values which were dropped by the most recent GC.
+    pub fn dropped_at_last_gc(&self) -> usize {
+        self.last_gc.dropped
+    }
+}
+
+/// The outcome of a namespace's most recent GC.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct GcCounts {
+    pub live: usize,
+    pub dropped: usize,
+}
+
+/// Receives the contents of a cache from [`crate::local::LocalCache::visit`] or
+/// [`crate::sync::SendCache::visit`].
+///
+/// The cache is borrowed while it's visited, so visitors must not access it.
+///
+/// # Example
+///
+/// ```
+/// use dyn_cache::{CacheVisitor, NamespaceStats, VisitedEntry};
+///
+/// #[derive(Default)]
+/// struct PrintScopes(Vec<String>);
+///
+/// impl CacheVisitor for PrintScopes {
+///     fn visit_namespace(&mut self, namespace: &NamespaceStats) {
+///         self.0.push(format!("{} stored {}:", namespace.output_type(), namespace.len()));
+///     }
+///
+///     fn visit_entry(&mut self, entry: VisitedEntry<'_>) {
+///         // scopes of types we know about can be printed
+///         let scope = match entry.scope::<char>() {
+///             Some(c) => format!("{:?}", c),
+///             None => entry.scope_type().to_owned(),
+///         };
+///         self.0.push(format!("  {} (live: {})", scope, entry.is_live()));
+///     }
+/// }
+///
+/// let storage = dyn_cache::local::SharedLocalCache::default();
+/// storage.hold(&'a', &(), |_| 1u8);
+///
+/// let mut printer = PrintScopes::default();
+/// storage.visit(&mut printer);
+/// assert_eq!(printer.0, ["u8 stored 1:", "  'a' (live: true)"]);
+/// ```
+pub trait CacheVisitor {
+    /// Called with each query type in the cache before its entries are visited.
+    fn visit_namespace(&mut self, _namespace: &NamespaceStats) {}
+
+    /// Called with each value stored for the most recently visited query type.
+    fn visit_entry(&mut self, entry: VisitedEntry<'_>);
+}
+
+/// Formats a scope of the type for which it was registered with
+/// [`crate::local::LocalCache::debug_scopes`] or
+/// [`crate::sync::SendCache::debug_scopes`].
+pub(crate) type DebugScope = fn(&dyn Any, &mut Formatter) -> FmtResult;
+
+/// A scope paired with the formatter registered for its type.
+#[derive(Clone, Copy)]
+pub(crate) struct ScopeDebug<'a> {
+    scope: &'a dyn Any,
+    debug: DebugScope,
+}
+
+impl<'a> ScopeDebug<'a> {
+    pub fn new(scope: &'a dyn Any, debug: DebugScope) -> Self {
+        Self { scope, debug }
+    }
+}
+
+impl Debug for ScopeDebug<'_> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        (self.debug)(self.scope, f)
+    }
+}
+
+/// A single value in a cache, passed to [`CacheVisitor::visit_entry`].
+#[derive(Clone, Copy)]
+pub struct VisitedEntry<'a> {
+    scope: &'a dyn Any,
+    scope_type: &'static str,
+    debug: Option<DebugScope>,
+    live: bool,
+}
+
+impl<'a> VisitedEntry<'a> {
+    pub(crate) fn new<Scope: 'static>(
+        scope: &'a Scope,
+        debug: Option<DebugScope>,
+        live: bool,
+    ) -> Self {
+        Self { scope, scope_type: std::any::type_name::<Scope>(), debug, live }
+    }
+
+    /// Returns the scope which indexes this value if it's a `T`.
+    pub fn scope<T: 'static>(&self) -> Option<&'a T> {
+        self.scope.downcast_ref()
+    }
+
+    /// Returns the name of the scope's type.
+    pub fn scope_type(&self) -> &'static str {
+        self.scope_type
+    }
+
+    /// Returns the scope formatted with [`Debug`] if its type was registered
+    /// with [`crate::local::LocalCache::debug_scopes`] or
+    /// [`crate::sync::SendCache::debug_scopes`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use dyn_cache::{
+    ///     local::{LocalCache, SharedLocalCache},
+    ///     CacheVisitor, VisitedEntry,
+    /// };
+    ///
+    /// struct Scopes(Vec<String>);
+    ///
+    /// impl CacheVisitor for Scopes {
+    ///     fn visit_entry(&mut self, entry: VisitedEntry<'_>) {
+    ///         self.0.push(format!("{:?}", entry.scope_debug().unwrap()));
+    ///     }
+    /// }
+    ///
+    /// let mut cache = LocalCache::default();
+    /// cache.debug_scopes::<String>();
+    /// let storage = SharedLocalCache::from(cache);
+    /// storage.hold("a", &(), |()| ());
+    ///
+    /// let mut scopes = Scopes(vec![]);
+    /// storage.visit(&mut scopes);
+    /// assert_eq!(scopes.0, ["\"a\""]);
+    /// ```
+    pub fn scope_debug(&self) -> Option<impl Debug + 'a> {
+        let scope = self.scope;
+        self.debug.map(|debug| ScopeDebug::new(scope, debug))
+    }
+
+    /// Returns true if the value has been read or written since the last GC,
+    /// in which case the next GC will retain it.
+    pub fn is_live(&self) -> bool {
+        self.live
+    }
+}
+
+impl Debug for VisitedEntry<'_> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut out = f.debug_struct("VisitedEntry");
+        match self.scope_debug() {
+            Some(scope) => out.field("scope", &scope),
+            None => out.field("scope", &self.scope_type),
+        };
+        out.field("live", &self.live).finish()
+    }
+}
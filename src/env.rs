@@ -0,0 +1,186 @@
+use std::{
+    any::TypeId,
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    marker::PhantomData,
+    ops::Deref,
+    rc::Rc,
+};
+
+/// A cached value stored along with the values its initialization read from
+/// the environment with [`crate::env`].
+pub(crate) struct Tracked<Output> {
+    output: Output,
+    reads: EnvReads,
+}
+
+impl<Output> Tracked<Output> {
+    /// Runs `init`, recording the values it reads.
+    pub fn init(init: impl FnOnce() -> Output) -> Self {
+        let reads = EnvReads::default();
+        let output = illicit::Layer::new().offer(reads.clone()).enter(init);
+        Self { output, reads }
+    }
+
+    /// Returns the output if none of the values read to initialize it have
+    /// changed.
+    ///
+    /// The reads count as made by the innermost cached value currently being
+    /// initialized, if any, as its output may depend on this one. That's the
+    /// case whether or not this value was just initialized.
+    pub fn current(&self) -> Option<&Output> {
+        let reads = self.reads.inner.borrow();
+        if reads.is_empty() {
+            return Some(&self.output);
+        }
+        if reads.values().any(|read| read.changed()) {
+            return None;
+        }
+        if let Ok(enclosing) = illicit::get::<EnvReads>() {
+            let mut enclosing = enclosing.inner.borrow_mut();
+            for (ty, read) in reads.iter() {
+                enclosing.entry(*ty).or_insert_with(|| read.clone());
+            }
+        }
+        Some(&self.output)
+    }
+}
+
+impl<Output: Debug> Debug for Tracked<Output> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Tracked")
+            .field("output", &self.output)
+            .field("reads", &self.reads.inner.borrow().len())
+            .finish()
+    }
+}
+
+/// The first read of each type made while initializing a cached value, offered
+/// via [`illicit`] to its `init`.
+#[derive(Clone, Default)]
+pub(crate) struct EnvReads {
+    inner: Rc<RefCell<HashMap<TypeId, Rc<dyn Read>>>>,
+}
+
+impl EnvReads {
+    /// Reads `E` from the environment, recording the read for the innermost
+    /// cached value currently being initialized, if any.
+    pub fn read<E>() -> Result<impl Deref<Target = E> + Debug + 'static, illicit::GetFailed>
+    where
+        E: PartialEq + Debug + 'static,
+    {
+        let value = illicit::get::<E>().map(|value| Shared(Rc::new(value)));
+        if let Ok(current) = illicit::get::<Self>() {
+            current.inner.borrow_mut().entry(TypeId::of::<E>()).or_insert_with(|| {
+                Rc::new(Recorded { value: value.as_ref().ok().cloned(), ty: PhantomData })
+            });
+        }
+        value
+    }
+}
+
+impl Debug for EnvReads {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("EnvReads").field("reads", &self.inner.borrow().len()).finish()
+    }
+}
+
+/// A value read from the environment, shared between the caller of
+/// [`crate::env`] and the record of the read.
+struct Shared<D>(Rc<D>);
+
+impl<D> Clone for Shared<D> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<D: Deref> Deref for Shared<D> {
+    type Target = D::Target;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<D: Debug> Debug for Shared<D> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        self.0.fmt(f)
+    }
+}
+
+/// A value read from the environment, with its type erased.
+trait Read {
+    /// Returns true if the environment now offers a different value.
+    fn changed(&self) -> bool;
+}
+
+struct Recorded<E, D> {
+    value: Option<D>,
+    ty: PhantomData<E>,
+}
+
+impl<E, D> Read for Recorded<E, D>
+where
+    E: PartialEq + Debug + 'static,
+    D: Deref<Target = E>,
+{
+    fn changed(&self) -> bool {
+        match (&self.value, illicit::get::<E>()) {
+            (Some(prev), Ok(current)) => **prev != *current,
+            (None, Err(_)) => false,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_reads_recorded_once() {
+        illicit::Layer::new().offer(1u8).enter(|| {
+            let tracked = Tracked::init(|| {
+                for _ in 0..1000 {
+                    EnvReads::read::<u8>().unwrap();
+                    assert!(EnvReads::read::<u16>().is_err());
+                }
+            });
+            assert_eq!(tracked.reads.inner.borrow().len(), 2, "one read per type");
+            assert!(tracked.current().is_some());
+        });
+    }
+
+    #[test]
+    fn changed_reads_are_not_current() {
+        let tracked = illicit::Layer::new()
+            .offer(1u8)
+            .enter(|| Tracked::init(|| *EnvReads::read::<u8>().unwrap()));
+        assert!(tracked.current().is_none(), "no longer offered");
+
+        illicit::Layer::new().offer(1u8).enter(|| {
+            assert_eq!(tracked.current(), Some(&1));
+        });
+        illicit::Layer::new().offer(2u8).enter(|| {
+            assert!(tracked.current().is_none(), "a different value is offered");
+        });
+    }
+
+    #[test]
+    fn nested_reads_are_read_by_enclosing() {
+        illicit::Layer::new().offer(1u8).enter(|| {
+            let inner = Tracked::init(|| EnvReads::read::<u8>().is_ok());
+            let outer = Tracked::init(|| inner.current().copied());
+            assert_eq!(outer.reads.inner.borrow().len(), 1, "read by an earlier init");
+
+            illicit::Layer::new().offer(2u8).enter(|| {
+                assert!(outer.current().is_none(), "the enclosing value read the changed value");
+            });
+        });
+
+        let untracked = Tracked::init(|| ());
+        assert!(untracked.reads.inner.borrow().is_empty());
+    }
+}
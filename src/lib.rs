@@ -33,6 +33,12 @@
 //! [`runtime::Revision`]s at the same callsite and are dropped from the cache
 //! at the end of the first revision where they were not used.
 //!
+//! Collections of keyed items can be cached per-item with [`cache_map`], which
+//! also reports how the items' order has changed since the previous revision.
+//!
+//! Cached values which read from the [`illicit`] environment with [`env`] are
+//! re-initialized when the environment offers a different value.
+//!
 //! ## State
 //!
 //! State variables are stored in the cache and can be mutated in between
@@ -41,6 +47,10 @@
 //! [`Key`] for updating it. Updates to state variables wake the runtime,
 //! initiating a new revision.
 //!
+//! A state variable can be shared with a whole subtree of callsites by
+//! declaring it with [`provide_state`] and reading it in descendants with
+//! [`use_provided`].
+//!
 //! ## Loading Futures
 //!
 //! Futures can be "loaded" by the runtime using the [`load`], [`load_with`],
@@ -50,23 +60,45 @@
 //! each revision. If a revision occurs without referencing the pending future,
 //! the task is cancelled.
 //!
+//! Callsites which need to share a single loading future can request it by key
+//! with [`resource`], which also supports refetching an outdated value while
+//! continuing to return the stale one.
+//!
+//! State variables can be updated optimistically while a future confirms the
+//! change with [`mutation`].
+//!
 //! [moxie-dom]: https://docs.rs/moxie-dom
 //! [topo]: https://docs.rs/topo/
 
 #![forbid(unsafe_code)]
 #![deny(clippy::all, missing_docs)]
 
+mod env;
+mod map;
+mod mutation;
+mod resource;
 pub mod runtime;
 pub mod testing;
+pub mod tree;
 
-use crate::runtime::{Context, Var};
+pub use map::{Change, Mapped};
+pub use mutation::Mutation;
+pub use resource::Resource;
+
+use crate::{
+    env::EnvReads,
+    mutation::Status,
+    runtime::{Context, Revision, Var},
+};
 use parking_lot::Mutex;
 use std::{
     borrow::Borrow,
+    cell::RefCell,
     fmt::{Debug, Display, Formatter, Result as FmtResult},
     future::Future,
     hash::{Hash, Hasher},
     ops::Deref,
+    panic::Location,
     sync::Arc,
     task::Poll,
 };
@@ -134,7 +166,7 @@ where
     Output: 'static,
     Ret: 'static,
 {
-    rt.cache.cache_with(&CallId::current(), arg, init, with)
+    rt.cache_with(&CallId::current(), arg, init, with)
 }
 
 /// Caches `init` once in the current [`topo::CallId`]. Runs `with` on every
@@ -175,7 +207,7 @@ where
     Output: 'static,
     Ret: 'static,
 {
-    rt.cache.cache_with(&CallId::current(), &(), |&()| init(), with)
+    rt.cache_with(&CallId::current(), &(), |&()| init(), with)
 }
 
 /// Memoizes `init` at this callsite, cloning a cached `Output` if it exists and
@@ -227,7 +259,7 @@ where
     Input: Borrow<Arg> + 'static,
     Output: Clone + 'static,
 {
-    rt.cache.cache(&CallId::current(), arg, init)
+    rt.cache_with(&CallId::current(), arg, init, Clone::clone)
 }
 
 /// Runs `init` once per [`topo::CallId`]. The provided value
@@ -261,7 +293,193 @@ pub fn once<Output>(init: impl FnOnce() -> Output) -> Output
 where
     Output: Clone + 'static,
 {
-    rt.cache.cache(&CallId::current(), &(), |()| init())
+    rt.cache_with(&CallId::current(), &(), |()| init(), Clone::clone)
+}
+
+/// Memoizes `init` at this callsite for up to `capacity` of the most recently
+/// used values of `Input`, cloning a cached `Output` if one was created from an
+/// equal `Input`.
+///
+/// Where [`cache`] only remembers the most recent `Input`, this avoids
+/// re-running `init` when a callsite alternates between a few inputs. When a
+/// new `Input` would exceed `capacity`, the least recently used `Output` is
+/// dropped.
+///
+/// # Example
+///
+/// ```
+/// use moxie::{cache_lru, runtime::RunLoop};
+/// use std::cell::Cell;
+///
+/// let tab = Cell::new("home");
+/// let num_renders = Cell::new(0);
+/// let mut rt = RunLoop::new(|| {
+///     cache_lru(tab.get(), 2, |tab| {
+///         num_renders.set(num_renders.get() + 1);
+///         tab.to_uppercase()
+///     })
+/// });
+///
+/// assert_eq!(rt.run_once(), "HOME");
+/// tab.set("settings");
+/// assert_eq!(rt.run_once(), "SETTINGS");
+/// tab.set("home");
+/// assert_eq!(rt.run_once(), "HOME");
+/// assert_eq!(num_renders.get(), 2, "switching back to a recent tab is cached");
+///
+/// tab.set("profile"); // evicts "settings"
+/// assert_eq!(rt.run_once(), "PROFILE");
+/// tab.set("settings");
+/// assert_eq!(rt.run_once(), "SETTINGS");
+/// assert_eq!(num_renders.get(), 4);
+/// ```
+///
+/// # Panics
+///
+/// If `capacity` is zero.
+#[topo::nested]
+#[illicit::from_env(rt: &Context)]
+pub fn cache_lru<Arg, Input, Output>(
+    arg: &Arg,
+    capacity: usize,
+    init: impl FnOnce(&Input) -> Output,
+) -> Output
+where
+    Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+    Input: 'static,
+    Output: Clone + 'static,
+{
+    rt.cache_lru_with(&CallId::current(), arg, capacity, init, Clone::clone)
+}
+
+/// Caches the result of `render` for each of `items`, returning the outputs in
+/// order along with the changes to the items' order since the last revision.
+///
+/// Each item is rendered within a [`topo::call_in_slot`] using the result of
+/// calling `key` on the item, so items retain their [`topo::CallId`] and any
+/// cached values or state within `render` when they move. `render` is only
+/// called for an item if it was not rendered in the previous revision or if it
+/// is unequal to the item from the previous revision with the same key.
+///
+/// Keys must be unique within `items`, which is checked in debug builds.
+///
+/// See [`Mapped::changes`] for details of how changes are reported.
+///
+/// # Example
+///
+/// ```
+/// use moxie::{cache_map, runtime::RunLoop, Change};
+/// use std::cell::{Cell, RefCell};
+///
+/// let items = RefCell::new(vec![(1, "one"), (2, "two"), (3, "three")]);
+/// let num_renders = Cell::new(0);
+///
+/// let mut rt = RunLoop::new(|| {
+///     cache_map(
+///         &items.borrow(),
+///         |(id, _)| *id,
+///         |(_, name)| {
+///             num_renders.set(num_renders.get() + 1);
+///             name.to_uppercase()
+///         },
+///     )
+/// });
+///
+/// let first = rt.run_once();
+/// assert_eq!(first.outputs(), ["ONE", "TWO", "THREE"]);
+/// assert_eq!(first.changes().len(), 3, "each item is inserted");
+/// assert_eq!(num_renders.get(), 3);
+///
+/// // move the first item to the end and rename the second
+/// items.replace(vec![(2, "deux"), (3, "three"), (1, "one")]);
+///
+/// let second = rt.run_once();
+/// assert_eq!(second.outputs(), ["DEUX", "THREE", "ONE"]);
+/// assert_eq!(second.changes(), [Change::Move { key: 1, from: 0, to: 2 }]);
+/// assert_eq!(num_renders.get(), 4, "only the renamed item is rendered again");
+///
+/// items.borrow_mut().remove(1);
+///
+/// let third = rt.run_once();
+/// assert_eq!(third.outputs(), ["DEUX", "ONE"]);
+/// assert_eq!(third.changes(), [Change::Remove { key: 3, index: 1 }]);
+/// assert_eq!(num_renders.get(), 4);
+/// ```
+#[topo::nested]
+#[illicit::from_env(rt: &Context)]
+pub fn cache_map<Item, K, Output>(
+    items: &[Item],
+    key: impl Fn(&Item) -> K,
+    mut render: impl FnMut(&Item) -> Output,
+) -> Mapped<K, Output>
+where
+    Item: Clone + PartialEq + 'static,
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+    Output: Clone + 'static,
+{
+    let keys: Vec<K> = items.iter().map(key).collect();
+    debug_assert_eq!(
+        keys.iter().collect::<std::collections::HashSet<_>>().len(),
+        keys.len(),
+        "cache_map keys must be unique",
+    );
+    let outputs = items
+        .iter()
+        .zip(&keys)
+        .map(|(item, key)| {
+            topo::call_in_slot(key, || {
+                rt.cache_with(&CallId::current(), item, |i| render(i), Clone::clone)
+            })
+        })
+        .collect();
+
+    rt.cache.cache_with(
+        &CallId::current(),
+        &(),
+        |()| RefCell::new(Vec::new()),
+        move |prev_keys| {
+            let prev = prev_keys.replace(keys);
+            Mapped::new(&prev, &prev_keys.borrow(), outputs)
+        },
+    )
+}
+
+/// Returns a reference to a value in the current [`illicit`] environment, as
+/// [`illicit::get`] does.
+///
+/// When called while initializing a value cached with [`cache`],
+/// [`cache_with`], [`cache_lru`], [`once`], [`once_with`] or [`cache_map`], the
+/// value read is recorded as an input of the innermost cached value and of
+/// every cached value whose initialization encloses it. They will be
+/// re-initialized in later revisions if the environment offers a value which
+/// is unequal to the one read, or if the value's presence changes.
+///
+/// # Example
+///
+/// ```
+/// use illicit::AsContext;
+/// use moxie::{env, once, runtime::RunLoop};
+/// use std::cell::Cell;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Theme(&'static str);
+///
+/// let theme = Cell::new("light");
+/// let mut rt = RunLoop::new(|| {
+///     Theme(theme.get()).offer(|| once(|| format!("{} button", env::<Theme>().unwrap().0)))
+/// });
+///
+/// assert_eq!(rt.run_once(), "light button");
+/// assert_eq!(rt.run_once(), "light button");
+///
+/// theme.set("dark");
+/// assert_eq!(rt.run_once(), "dark button", "changed theme must re-initialize the button");
+/// ```
+pub fn env<E>() -> Result<impl Deref<Target = E> + Debug + 'static, illicit::GetFailed>
+where
+    E: PartialEq + Debug + 'static,
+{
+    EnvReads::read()
 }
 
 /// Root a state variable at this callsite, returning a [`Key`] to the state
@@ -383,6 +601,72 @@ where
     rt.cache_state(&CallId::current(), arg, init)
 }
 
+/// Root a state variable at this callsite and offer its [`Key`] to the
+/// environment of `children`, returning their result. Descendants read the
+/// key by its state type with [`use_provided`].
+///
+/// # Example
+///
+/// ```
+/// use moxie::{provide_state, runtime::RunLoop, use_provided, Key};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Theme {
+///     dark: bool,
+/// }
+///
+/// fn label() -> &'static str {
+///     let theme: Key<Theme> = use_provided().unwrap();
+///     if theme.dark { "dark" } else { "light" }
+/// }
+///
+/// let mut rt = RunLoop::new(|| {
+///     provide_state(|| Theme { dark: false }, || (use_provided::<Theme>().unwrap(), label()))
+/// });
+///
+/// let (theme, first) = rt.run_once();
+/// assert_eq!(first, "light");
+///
+/// theme.set(Theme { dark: true });
+/// let (_, second) = rt.run_once();
+/// assert_eq!(second, "dark");
+/// ```
+#[topo::nested]
+pub fn provide_state<State, Ret>(
+    init: impl FnOnce() -> State,
+    children: impl FnOnce() -> Ret,
+) -> Ret
+where
+    State: Debug + 'static,
+{
+    let (_, key) = state(init);
+    illicit::Layer::new().offer(key).enter(children)
+}
+
+/// Returns the [`Key`] to the nearest state variable of type `State` offered
+/// by an enclosing call to [`provide_state`]. The runtime records the current
+/// callsite as a consumer of the provider, see
+/// [`runtime::Runtime::consumers_of`].
+///
+/// Returns [`illicit::GetFailed`] describing the current environment if no
+/// enclosing call provides a `State`:
+///
+/// ```
+/// assert!(moxie::use_provided::<u8>().is_err());
+/// ```
+///
+/// See [`provide_state`] for an example.
+pub fn use_provided<State>() -> Result<Key<State>, illicit::GetFailed>
+where
+    State: Debug + 'static,
+{
+    let key = illicit::get::<Key<State>>()?.clone();
+    if let Ok(rt) = illicit::get::<Context>() {
+        rt.consume(key.id());
+    }
+    Ok(key)
+}
+
 /// Load a value from the future returned by `init` whenever `capture` changes,
 /// returning the result of calling `with` with the loaded value. Cancels the
 /// running future after any revision during which this call was not made.
@@ -617,6 +901,202 @@ where
     rt.load_with(&CallId::current(), capture, init, Clone::clone)
 }
 
+/// Load a value from the future returned by `fetch`, sharing it with every
+/// other call to `resource` which uses an equal `key` within the runtime.
+///
+/// Unlike [`load`], the future is identified by `key` rather than the current
+/// [`topo::CallId`], so any number of callsites requesting the same key will
+/// share a single in-flight future and its result. `fetch` is only called when
+/// no value has been requested for the key yet or when the resource has been
+/// invalidated with [`Resource::invalidate`]. While a refetch is running, the
+/// previously fetched value is still returned from [`Resource::value`].
+///
+/// If a revision occurs without any callsite requesting `key`, the resource
+/// is dropped from the cache and its running future (if any) is cancelled.
+///
+/// Because resources are stored by the type of their key, keys should use a
+/// type distinct to the kind of resource they identify.
+///
+/// # Example
+///
+/// ```
+/// use futures::{channel::oneshot, executor::LocalPool};
+/// use moxie::{resource, runtime::RunLoop};
+/// use std::sync::mpsc::channel;
+///
+/// let (send_futs, recv_futs) = channel();
+///
+/// let mut rt = RunLoop::new(|| {
+///     let fetch = |id: &u32| {
+///         let (sender, receiver) = oneshot::channel();
+///         send_futs.send((*id, sender)).unwrap();
+///         receiver
+///     };
+///
+///     // two callsites requesting the same key share a single future
+///     let first = resource(&1u32, fetch);
+///     let second = resource(&1u32, fetch);
+///     (first.value().cloned(), second.value().cloned(), first)
+/// });
+///
+/// let mut exec = LocalPool::new();
+/// rt.set_task_executor(exec.spawner());
+///
+/// let (first, second, _) = rt.run_once();
+/// assert_eq!((first, second), (None, None));
+///
+/// let (id, sender) = recv_futs.recv().unwrap();
+/// assert!(recv_futs.try_recv().is_err(), "only one future is created per key");
+/// assert_eq!(id, 1);
+///
+/// sender.send("hello").unwrap();
+/// exec.run_until_stalled();
+///
+/// let (first, second, res) = rt.run_once();
+/// assert_eq!(first, Some(Ok("hello")));
+/// assert_eq!(second, Some(Ok("hello")));
+///
+/// // refetching continues to offer the stale value
+/// res.invalidate();
+/// let (first, _, res) = rt.run_once();
+/// assert_eq!(first, Some(Ok("hello")));
+/// assert!(res.is_stale());
+///
+/// let (_, sender) = recv_futs.recv().unwrap();
+/// sender.send("world").unwrap();
+/// exec.run_until_stalled();
+///
+/// let (first, _, res) = rt.run_once();
+/// assert_eq!(first, Some(Ok("world")));
+/// assert!(!res.is_fetching());
+/// ```
+#[illicit::from_env(rt: &Context)]
+pub fn resource<Arg, Scope, Fut, Output>(
+    key: &Arg,
+    fetch: impl FnOnce(&Arg) -> Fut,
+) -> Resource<Output>
+where
+    Arg: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+    Scope: Borrow<Arg> + Eq + Hash + 'static,
+    Fut: Future<Output = Output> + 'static,
+    Output: 'static,
+{
+    rt.resource(key, fetch)
+}
+
+/// Optimistically update the state variable behind `key` and spawn `future` to
+/// confirm the update, returning a [`Mutation`] which tracks its progress.
+///
+/// `optimistic` is called immediately with the variable's latest value and its
+/// result is enqueued as a commit, as with [`Key::update`]. `future` is then
+/// spawned on the executor of the current runtime. If it
+/// returns `Ok`, the returned value is committed to the state variable.
+/// Otherwise the variable is reverted to its value from before the optimistic
+/// update and the error is retained by the [`Mutation`].
+///
+/// If the state variable is updated again while `future` is pending, the newer
+/// value takes precedence and is not overwritten when the mutation completes.
+///
+/// # Panics
+///
+/// If called outside of a [`runtime::Revision`], or if the current
+/// [`runtime::Runtime`] did not have a valid call to `set_task_executor`.
+///
+/// # Example
+///
+/// ```
+/// use futures::{channel::oneshot, executor::LocalPool};
+/// use moxie::{mutation, runtime::Runtime, state};
+///
+/// let mut rt = Runtime::new();
+/// let mut exec = LocalPool::new();
+/// rt.set_task_executor(exec.spawner());
+/// let count = || state(|| 0u32);
+///
+/// let (respond, response) = oneshot::channel::<Result<u32, &str>>();
+/// let saving = rt.run_once(|| {
+///     let (_, count) = count();
+///     mutation(&count, |prev| Some(prev + 1), async move { response.await.unwrap() })
+/// });
+///
+/// let (optimistic, _) = rt.run_once(count);
+/// assert_eq!(*optimistic, 1, "optimistic update is visible before the future completes");
+/// assert!(saving.is_pending());
+///
+/// respond.send(Err("rejected by server")).unwrap();
+/// exec.run_until_stalled();
+///
+/// let (reverted, _) = rt.run_once(count);
+/// assert_eq!(*reverted, 0, "failed mutations are reverted");
+/// assert_eq!(saving.error().as_deref(), Some(&"rejected by server"));
+///
+/// let (respond, response) = oneshot::channel::<Result<u32, &str>>();
+/// let saving = rt.run_once(|| {
+///     let (_, count) = count();
+///     mutation(&count, |prev| Some(prev + 1), async move { response.await.unwrap() })
+/// });
+/// respond.send(Ok(5)).unwrap();
+/// exec.run_until_stalled();
+///
+/// let (committed, _) = rt.run_once(count);
+/// assert_eq!(*committed, 5, "the future's output replaces the optimistic value");
+/// assert!(saving.is_committed());
+/// ```
+#[track_caller]
+#[illicit::from_env(rt: &Context)]
+pub fn mutation<State, Fut, Error>(
+    key: &Key<State>,
+    optimistic: impl FnOnce(&State) -> Option<State>,
+    future: Fut,
+) -> Mutation<Error>
+where
+    State: 'static,
+    Fut: Future<Output = Result<State, Error>> + 'static,
+    Error: 'static,
+{
+    let (before, after) = {
+        let mut var = key.var.lock();
+        let before = var.latest_commit().clone();
+        if let Some(new) = optimistic(&before) {
+            var.enqueue_commit(new);
+        }
+        (before, var.latest_commit().clone())
+    };
+
+    let mutation = Mutation::pending();
+    let (var, status) = (key.var.clone(), mutation.status.clone());
+    let location = Location::caller();
+    let task = async move {
+        let result = future.await;
+        let mut var = var.lock();
+
+        // a newer update to the variable takes precedence over this one's outcome
+        let superseded = !Arc::ptr_eq(&var.latest_commit().inner, &after.inner);
+        match result {
+            Ok(confirmed) => {
+                if !superseded {
+                    var.enqueue_commit_from(confirmed, location);
+                }
+                *status.borrow_mut() = Status::Committed;
+            }
+            Err(e) => {
+                if !superseded {
+                    var.enqueue(before, location);
+                }
+                *status.borrow_mut() = Status::Failed(e);
+            }
+        }
+
+        if superseded {
+            // nothing was enqueued but the new status still needs to be rendered
+            var.wake(location);
+        }
+    };
+    rt.spawn(task);
+
+    mutation
+}
+
 /// A read-only pointer to the value of a state variable *at a particular
 /// revision*.
 ///
@@ -626,15 +1106,67 @@ where
 /// each time.
 ///
 /// See [`state`] and [`cache_state`] for examples.
-#[derive(Eq, Hash, PartialEq)]
 pub struct Commit<State> {
     id: CallId,
     inner: Arc<State>,
+    revision: Revision,
+}
+
+impl<State> Commit<State> {
+    /// Returns the [`runtime::Revision`] during which this commit was rooted,
+    /// i.e. the first revision in which its value was visible to the runtime.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use moxie::{
+    ///     runtime::{Revision, RunLoop},
+    ///     state,
+    /// };
+    ///
+    /// let mut rt = RunLoop::new(|| state(|| 0u64));
+    ///
+    /// let (first_commit, key) = rt.run_once();
+    /// assert_eq!(first_commit.revision(), Revision(1));
+    ///
+    /// let (unchanged, _) = rt.run_once();
+    /// assert_eq!(unchanged.revision(), Revision(1), "no new commits were made");
+    ///
+    /// key.set(1);
+    /// let (second_commit, _) = rt.run_once();
+    /// assert_eq!(second_commit.revision(), Revision(3));
+    /// ```
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
 }
 
 impl<State> Clone for Commit<State> {
     fn clone(&self) -> Self {
-        Self { id: self.id, inner: Arc::clone(&self.inner) }
+        Self { id: self.id, inner: Arc::clone(&self.inner), revision: self.revision }
+    }
+}
+
+impl<State> PartialEq for Commit<State>
+where
+    State: PartialEq,
+{
+    /// Commits are compared by their state variable and value, regardless of
+    /// the revision in which they were rooted.
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.inner == other.inner
+    }
+}
+
+impl<State> Eq for Commit<State> where State: Eq {}
+
+impl<State> Hash for Commit<State>
+where
+    State: Hash,
+{
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.id.hash(hasher);
+        self.inner.hash(hasher);
     }
 }
 
@@ -683,10 +1215,48 @@ impl<State> Key<State> {
         self.id
     }
 
+    /// Returns the [`runtime::Revision`] during which the state variable's
+    /// latest committed value was rooted. Pending commits are not included
+    /// until they are rooted in a subsequent revision.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use moxie::{
+    ///     runtime::{Revision, RunLoop},
+    ///     state,
+    /// };
+    ///
+    /// let mut rt = RunLoop::new(|| state(|| 0u64));
+    ///
+    /// let (_, key) = rt.run_once();
+    /// rt.run_once();
+    /// assert_eq!(key.last_changed(), Revision(1));
+    /// assert!(!key.changed_since(Revision(1)));
+    ///
+    /// key.set(1);
+    /// assert_eq!(key.last_changed(), Revision(1), "update is only pending");
+    ///
+    /// rt.run_once();
+    /// assert_eq!(key.last_changed(), Revision(3));
+    /// assert!(key.changed_since(Revision(2)));
+    /// ```
+    pub fn last_changed(&self) -> Revision {
+        self.var.lock().committed().revision
+    }
+
+    /// Returns true if the state variable has received a commit which was
+    /// rooted after `revision`. See [`Key::last_changed`].
+    pub fn changed_since(&self, revision: Revision) -> bool {
+        self.last_changed() > revision
+    }
+
     /// Runs `updater` with a reference to the state variable's latest value,
     /// and enqueues a commit to the variable if `updater` returns `Some`.
     /// Returns the `Revision` at which the state variable was last rooted
-    /// if the variable is live, otherwise returns `None`.
+    /// if the variable is live, otherwise returns `None`. See
+    /// [`Key::last_changed`] for the `Revision` at which the state variable
+    /// last received a commit.
     ///
     /// Enqueuing the commit invokes the state change waker registered with the
     /// [Runtime] (if any) to ensure that the code embedding the runtime
@@ -703,7 +1273,11 @@ impl<State> Key<State> {
     ///
     /// ```
     /// use futures::task::waker;
-    /// use moxie::{runtime::RunLoop, state, testing::BoolWaker};
+    /// use moxie::{
+    ///     runtime::{Revision, RunLoop},
+    ///     state,
+    ///     testing::BoolWaker,
+    /// };
     ///
     /// // this runtime holds a single state variable
     /// let mut rt = RunLoop::new(|| state(|| 0u64));
@@ -715,7 +1289,8 @@ impl<State> Key<State> {
     /// assert_eq!(*first_commit, 0, "no updates yet");
     /// assert!(!track_wakes.is_woken(), "no updates yet");
     ///
-    /// first_key.update(|_| None); // this is a no-op
+    /// let rooted = first_key.update(|_| None); // this is a no-op
+    /// assert_eq!(rooted, Some(Revision(1)), "rooted during the first revision");
     /// assert_eq!(*first_key, 0, "no updates yet");
     /// assert!(!track_wakes.is_woken(), "no updates yet");
     ///
@@ -730,14 +1305,17 @@ impl<State> Key<State> {
     /// assert!(!track_wakes.is_woken(), "wakes only come from updating state vars");
     /// assert_eq!(first_key, second_key, "same state variable");
     /// ```
-    pub fn update(&self, updater: impl FnOnce(&State) -> Option<State>) {
+    #[track_caller]
+    pub fn update(&self, updater: impl FnOnce(&State) -> Option<State>) -> Option<Revision> {
         let mut var = self.var.lock();
         if let Some(new) = updater(var.latest()) {
             var.enqueue_commit(new);
         }
+        var.last_rooted()
     }
 
     /// Set a new value for the state variable, immediately taking effect.
+    #[track_caller]
     fn force(&self, new: State) {
         self.var.lock().enqueue_commit(new);
     }
@@ -757,6 +1335,7 @@ where
     /// [update](Key::update) regarding waking the runtime.
     ///
     /// See [`state`] and [`cache_state`] for examples.
+    #[track_caller]
     pub fn set(&self, new: State) {
         self.update(|prev| if prev == &new { None } else { Some(new) });
     }
@@ -769,6 +1348,7 @@ where
     /// Mutates a copy of the current state, committing the update if it results
     /// in a change. Has the same properties as [update](Key::update)
     /// See [`state`] and [`cache_state`] for examples.
+    #[track_caller]
     pub fn mutate(&self, op: impl FnOnce(&mut State)) {
         self.update(|prev| {
             let mut new = prev.clone();
@@ -835,7 +1415,7 @@ impl<State> Hash for Key<State> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::runtime::{Revision, RunLoop};
+    use crate::runtime::{Revision, RunLoop, Runtime};
     use std::{cell::Cell, collections::HashSet, rc::Rc};
 
     fn with_test_logs(test: impl FnOnce()) {
@@ -1044,4 +1624,223 @@ mod tests {
             "must be no task holding the channel and able to receive a message"
         );
     }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "cache_map keys must be unique")]
+    fn cache_map_rejects_duplicate_keys() {
+        RunLoop::new(|| cache_map(&[1, 2, 1], |n| *n, |n| *n)).run_once();
+    }
+
+    #[test]
+    fn failed_mutation_keeps_newer_updates() {
+        let mut pool = futures::executor::LocalPool::new();
+        let mut rt = Runtime::new();
+        rt.set_task_executor(pool.spawner());
+        let root = || state(|| 0u8);
+        let (_, key) = rt.run_once(root);
+
+        let (respond, response) = futures::channel::oneshot::channel::<Result<u8, ()>>();
+        let first = rt
+            .run_once(|| mutation(&root().1, |_| Some(1), async move { response.await.unwrap() }));
+        assert_eq!(*rt.run_once(root).0, 1, "optimistic update must be committed");
+
+        key.set(2);
+        respond.send(Err(())).unwrap();
+        pool.run_until_stalled();
+        assert!(first.is_failed());
+        assert_eq!(*rt.run_once(root).0, 2, "newer update must not be reverted");
+
+        let (respond, response) = futures::channel::oneshot::channel::<Result<u8, ()>>();
+        let second =
+            rt.run_once(|| mutation(&root().1, |_| None, async move { response.await.unwrap() }));
+        assert!(second.is_pending());
+        assert_eq!(*rt.run_once(root).0, 2, "no optimistic update was made");
+
+        respond.send(Ok(3)).unwrap();
+        pool.run_until_stalled();
+        assert!(second.is_committed());
+        assert_eq!(*rt.run_once(root).0, 3, "confirmed value must be committed");
+    }
+
+    #[test]
+    fn update_returns_last_rooted_revision() {
+        let rooted = Cell::new(true);
+        let mut rt = RunLoop::new(|| if rooted.get() { Some(state(|| 0u8).1) } else { None });
+        let key = rt.run_once().unwrap();
+        rt.run_once();
+        assert_eq!(key.update(|_| None), Some(Revision(2)));
+
+        rooted.set(false);
+        rt.run_once();
+        assert_eq!(key.update(|n| Some(n + 1)), None, "variable is no longer live");
+    }
+
+    #[test]
+    fn mutation_uses_current_executor() {
+        let mut rt = Runtime::new();
+        let root = || state(|| 0u8);
+        rt.run_once(root);
+
+        // the executor is set after the state variable was created
+        let mut pool = futures::executor::LocalPool::new();
+        rt.set_task_executor(pool.spawner());
+
+        let saving = rt.run_once(|| mutation(&root().1, |_| None, async { Ok::<_, ()>(1) }));
+        pool.run_until_stalled();
+        assert!(saving.is_committed());
+        assert_eq!(*rt.run_once(root).0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "that set_task_executor has been called")]
+    fn mutation_without_executor_panics() {
+        let mut rt = Runtime::new();
+        rt.run_once(|| mutation(&state(|| 0u8).1, |_| None, async { Ok::<_, ()>(1) }));
+    }
+
+    #[test]
+    fn env_reads_reinitialize_cache() {
+        let inits = Cell::new(0);
+        let offered = Cell::new(None);
+
+        let mut rt = RunLoop::new(|| {
+            let mut layer = illicit::Layer::new();
+            if let Some(n) = offered.get() {
+                layer = layer.offer(n);
+            }
+            let read = layer.enter(|| {
+                once(|| {
+                    inits.set(inits.get() + 1);
+                    env::<u8>().map(|n| *n).ok()
+                })
+            });
+            (read, inits.get())
+        });
+
+        assert_eq!(rt.run_once(), (None, 1));
+        assert_eq!(rt.run_once(), (None, 1), "absent value is unchanged");
+
+        offered.set(Some(1u8));
+        assert_eq!(rt.run_once(), (Some(1), 2), "value became present");
+        assert_eq!(rt.run_once(), (Some(1), 2), "value is unchanged");
+
+        offered.set(Some(2u8));
+        assert_eq!(rt.run_once(), (Some(2), 3), "value changed");
+    }
+
+    #[test]
+    fn nested_env_reads_reinitialize_enclosing_cache() {
+        let offered = Cell::new(1u32);
+        let mut rt = RunLoop::new(|| {
+            illicit::Layer::new()
+                .offer(offered.get())
+                .enter(|| cache(&(), |_| cache(&(), |_| *env::<u32>().unwrap())))
+        });
+
+        assert_eq!(rt.run_once(), 1);
+        assert_eq!(rt.run_once(), 1);
+
+        offered.set(2);
+        assert_eq!(rt.run_once(), 2, "the outer value is re-initialized with the inner one");
+        assert_eq!(rt.run_once(), 2);
+    }
+
+    #[test]
+    fn resource_interest_loss_cancels_task() {
+        let mut pool = futures::executor::LocalPool::new();
+        let (send, recv) = futures::channel::oneshot::channel::<u8>();
+        let mut recv = Some(recv);
+
+        let mut rt = RunLoop::new(move || -> Option<bool> {
+            if Revision::current().0 < 3 {
+                let first = resource("shared", |_| recv.take().unwrap());
+                let second = resource("shared", |_| -> futures::channel::oneshot::Receiver<u8> {
+                    unreachable!("the first callsite already spawned a future for this key")
+                });
+                assert!(first.state == second.state, "same key must share a state variable");
+                Some(first.is_fetching())
+            } else {
+                None
+            }
+        });
+        rt.set_task_executor(pool.spawner());
+
+        assert_eq!(rt.run_once(), Some(true));
+        pool.run_until_stalled();
+        assert!(!send.is_canceled(), "interest expressed, receiver must be live");
+
+        assert_eq!(rt.run_once(), Some(true));
+        pool.run_until_stalled();
+        assert!(!send.is_canceled(), "interest still expressed, receiver must be live");
+
+        assert_eq!(rt.run_once(), None);
+        pool.run_until_stalled();
+        assert!(send.is_canceled(), "interest dropped, task dropped");
+    }
+
+    #[test]
+    fn provided_state_tracks_consumers() {
+        #[topo::nested]
+        fn consumer() -> topo::CallId {
+            use_provided::<u8>().unwrap();
+            topo::CallId::current()
+        }
+
+        let include_second = Rc::new(Cell::new(true));
+        let include = include_second.clone();
+        let mut rt = RunLoop::new(move || {
+            provide_state(
+                || 0u8,
+                || {
+                    let provider = illicit::get::<Key<u8>>().unwrap().id();
+                    let first = consumer();
+                    let second = if include.get() { Some(consumer()) } else { None };
+                    (provider, first, second)
+                },
+            )
+        });
+
+        let (provider, first, second) = rt.run_once();
+        assert_eq!(rt.consumers_of(&provider), [first, second.unwrap()]);
+
+        include_second.set(false);
+        let (provider, first, second) = rt.run_once();
+        assert_eq!(second, None);
+        assert_eq!(rt.consumers_of(&provider), [first], "unmade reads are dropped");
+    }
+
+    #[test]
+    fn nearest_provider_shadows() {
+        #[topo::nested]
+        fn consumer() -> (u8, topo::CallId) {
+            (*use_provided::<u8>().unwrap(), topo::CallId::current())
+        }
+
+        let mut rt = RunLoop::new(|| {
+            provide_state(
+                || 1u8,
+                || {
+                    let outer = illicit::get::<Key<u8>>().unwrap().id();
+                    let (inner, read) = provide_state(
+                        || 2u8,
+                        || (illicit::get::<Key<u8>>().unwrap().id(), consumer()),
+                    );
+                    (outer, inner, read)
+                },
+            )
+        });
+
+        let (outer, inner, (value, consumer)) = rt.run_once();
+        assert_eq!(value, 2, "reads the nearest provided value");
+        assert_eq!(rt.consumers_of(&inner), [consumer]);
+        assert!(rt.consumers_of(&outer).is_empty(), "shadowed provider has no consumers");
+    }
+
+    #[test]
+    fn use_provided_without_provider_fails() {
+        let mut rt = RunLoop::new(|| use_provided::<u8>().map(|_| ()).unwrap_err().to_string());
+        let message = rt.run_once();
+        assert!(message.contains("u8"), "error names the missing type: {}", message);
+    }
 }
@@ -0,0 +1,183 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+/// A change to the position of a keyed item between two revisions, as
+/// reported by [`crate::cache_map`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Change<K> {
+    /// The item was not present in the previous revision and is now at
+    /// `index`.
+    Insert {
+        /// The key of the inserted item.
+        key: K,
+        /// The item's position in the current revision.
+        index: usize,
+    },
+    /// The item was present at `index` in the previous revision and is no
+    /// longer present.
+    Remove {
+        /// The key of the removed item.
+        key: K,
+        /// The item's position in the previous revision.
+        index: usize,
+    },
+    /// The item was present in both revisions but its order relative to the
+    /// other retained items has changed.
+    Move {
+        /// The key of the moved item.
+        key: K,
+        /// The item's position in the previous revision.
+        from: usize,
+        /// The item's position in the current revision.
+        to: usize,
+    },
+}
+
+/// The outputs of [`crate::cache_map`] in the order of its items, along with
+/// the changes to the items' order since the previous revision.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mapped<K, Output> {
+    outputs: Vec<Output>,
+    changes: Vec<Change<K>>,
+}
+
+impl<K, Output> Mapped<K, Output> {
+    /// Returns the outputs for each item, in order.
+    pub fn outputs(&self) -> &[Output] {
+        &self.outputs
+    }
+
+    /// Returns the outputs for each item, in order.
+    pub fn into_outputs(self) -> Vec<Output> {
+        self.outputs
+    }
+
+    /// Returns the changes required to transform the previous revision's list
+    /// of items into this one. Removals are listed first in order of their
+    /// previous index, followed by insertions and moves in order of their
+    /// current index.
+    ///
+    /// Moves are minimal: items whose order relative to each other is
+    /// unchanged are not reported as moved even if their indices changed.
+    pub fn changes(&self) -> &[Change<K>] {
+        &self.changes
+    }
+}
+
+impl<K, Output> Mapped<K, Output>
+where
+    K: Clone + Eq + Hash,
+{
+    pub(crate) fn new(prev: &[K], current: &[K], outputs: Vec<Output>) -> Self {
+        let prev_indices: HashMap<&K, usize> =
+            prev.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let current_keys: HashSet<&K> = current.iter().collect();
+
+        let mut changes: Vec<_> = prev
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| !current_keys.contains(key))
+            .map(|(index, key)| Change::Remove { key: key.clone(), index })
+            .collect();
+
+        // retained items which are part of the longest run that kept its order don't need to move
+        let retained: Vec<(usize, usize)> = current
+            .iter()
+            .enumerate()
+            .filter_map(|(to, key)| prev_indices.get(key).map(|&from| (from, to)))
+            .collect();
+        let mut in_order = longest_increasing(&retained).into_iter().peekable();
+        let mut moved = HashMap::new();
+        for (i, &(from, to)) in retained.iter().enumerate() {
+            if in_order.peek() == Some(&i) {
+                in_order.next();
+            } else {
+                moved.insert(to, from);
+            }
+        }
+
+        for (index, key) in current.iter().enumerate() {
+            if let Some(&from) = moved.get(&index) {
+                changes.push(Change::Move { key: key.clone(), from, to: index });
+            } else if !prev_indices.contains_key(key) {
+                changes.push(Change::Insert { key: key.clone(), index });
+            }
+        }
+
+        Self { outputs, changes }
+    }
+}
+
+/// Returns the indices within `pairs` of the longest subsequence whose first
+/// elements are strictly increasing.
+fn longest_increasing(pairs: &[(usize, usize)]) -> Vec<usize> {
+    // tails[n] is the index of the smallest tail of any increasing run of length n + 1
+    let mut tails: Vec<usize> = Vec::new();
+    let mut predecessors = vec![None; pairs.len()];
+
+    for (i, &(value, _)) in pairs.iter().enumerate() {
+        let len = tails.partition_point(|&t| pairs[t].0 < value);
+        if len > 0 {
+            predecessors[i] = Some(tails[len - 1]);
+        }
+        if len == tails.len() {
+            tails.push(i);
+        } else {
+            tails[len] = i;
+        }
+    }
+
+    let mut run = Vec::with_capacity(tails.len());
+    let mut next = tails.last().copied();
+    while let Some(i) = next {
+        run.push(i);
+        next = predecessors[i];
+    }
+    run.reverse();
+    run
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(prev: &[char], current: &[char]) -> Vec<Change<char>> {
+        Mapped::new(prev, current, vec![(); current.len()]).changes
+    }
+
+    #[test]
+    fn no_changes() {
+        assert_eq!(changes(&['a', 'b', 'c'], &['a', 'b', 'c']), vec![]);
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        assert_eq!(
+            changes(&['a', 'b', 'c'], &['a', 'd', 'c']),
+            vec![Change::Remove { key: 'b', index: 1 }, Change::Insert { key: 'd', index: 1 }]
+        );
+    }
+
+    #[test]
+    fn shifted_items_do_not_move() {
+        assert_eq!(
+            changes(&['b', 'c', 'd'], &['a', 'b', 'c', 'd']),
+            vec![Change::Insert { key: 'a', index: 0 }]
+        );
+    }
+
+    #[test]
+    fn minimal_moves() {
+        assert_eq!(
+            changes(&['a', 'b', 'c', 'd'], &['d', 'a', 'b', 'c']),
+            vec![Change::Move { key: 'd', from: 3, to: 0 }]
+        );
+        assert_eq!(
+            changes(&['a', 'b', 'c', 'd'], &['d', 'c', 'b', 'a']).len(),
+            3,
+            "reversing four items requires three moves"
+        );
+    }
+}
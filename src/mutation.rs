@@ -0,0 +1,83 @@
+use std::{
+    cell::{Ref, RefCell},
+    fmt::{Debug, Formatter, Result as FmtResult},
+    rc::Rc,
+};
+
+/// The outcome of a [`crate::mutation`], shared between the task running its
+/// future and any [`Mutation`] handles.
+pub(crate) enum Status<Error> {
+    Pending,
+    Committed,
+    Failed(Error),
+}
+
+/// A handle to the status of a mutation started with [`crate::mutation`].
+///
+/// Mutations are pending until their future completes, after which they are
+/// either committed or failed. Failed mutations retain the error returned by
+/// their future for rendering.
+///
+/// See [`crate::mutation`] for examples.
+pub struct Mutation<Error> {
+    pub(crate) status: Rc<RefCell<Status<Error>>>,
+}
+
+impl<Error> Mutation<Error> {
+    pub(crate) fn pending() -> Self {
+        Self { status: Rc::new(RefCell::new(Status::Pending)) }
+    }
+
+    /// Returns true if the mutation's future has not yet completed.
+    pub fn is_pending(&self) -> bool {
+        matches!(&*self.status.borrow(), Status::Pending)
+    }
+
+    /// Returns true if the mutation's future completed successfully.
+    pub fn is_committed(&self) -> bool {
+        matches!(&*self.status.borrow(), Status::Committed)
+    }
+
+    /// Returns true if the mutation's future returned an error.
+    pub fn is_failed(&self) -> bool {
+        matches!(&*self.status.borrow(), Status::Failed(_))
+    }
+
+    /// Returns the error returned by the mutation's future, if it failed.
+    pub fn error(&self) -> Option<Ref<'_, Error>> {
+        Ref::filter_map(self.status.borrow(), |status| match status {
+            Status::Failed(e) => Some(e),
+            _ => None,
+        })
+        .ok()
+    }
+}
+
+impl<Error> Clone for Mutation<Error> {
+    fn clone(&self) -> Self {
+        Self { status: self.status.clone() }
+    }
+}
+
+impl<Error> Debug for Mutation<Error>
+where
+    Error: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &*self.status.borrow() {
+            Status::Pending => f.write_str("Mutation::Pending"),
+            Status::Committed => f.write_str("Mutation::Committed"),
+            Status::Failed(e) => f.debug_tuple("Mutation::Failed").field(e).finish(),
+        }
+    }
+}
+
+impl<Error> PartialEq for Mutation<Error> {
+    /// Mutations are considered equal if they are handles to the same
+    /// invocation of [`crate::mutation`].
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.status, &other.status)
+    }
+}
+
+impl<Error> Eq for Mutation<Error> {}
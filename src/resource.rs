@@ -0,0 +1,110 @@
+use crate::Key;
+use futures::future::AbortHandle;
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+/// The shared state of a [`Resource`], stored in a state variable which is
+/// cached under the resource's key.
+pub(crate) struct Fetch<Output> {
+    /// The most recently fetched value, if any.
+    pub value: Option<Rc<Output>>,
+    /// Incremented each time the resource is invalidated.
+    pub generation: u64,
+    /// The generation which produced `value`.
+    pub fetched: Option<u64>,
+}
+
+impl<Output> Default for Fetch<Output> {
+    fn default() -> Self {
+        Self { value: None, generation: 0, fetched: None }
+    }
+}
+
+/// Cancels a resource's in-flight future when dropped from the cache. Typed by
+/// the resource's output so that resources with the same type of key don't
+/// share storage.
+pub(crate) struct InFlight<Output> {
+    aborter: AbortHandle,
+    ty: PhantomData<Output>,
+}
+
+impl<Output> InFlight<Output> {
+    pub fn new(aborter: AbortHandle) -> Self {
+        Self { aborter, ty: PhantomData }
+    }
+}
+
+impl<Output> Drop for InFlight<Output> {
+    fn drop(&mut self) {
+        self.aborter.abort();
+    }
+}
+
+/// A value loaded from a future which is shared by every caller of
+/// [`crate::resource`] with the same key during a [`crate::runtime::Revision`].
+///
+/// Once a value has been fetched it remains available while the resource is
+/// refetched after calling [`Resource::invalidate`], allowing callers to
+/// render a "stale" value while a fresh one loads.
+///
+/// See [`crate::resource`] for examples.
+pub struct Resource<Output> {
+    pub(crate) state: Key<Fetch<Output>>,
+}
+
+impl<Output> Resource<Output> {
+    /// Returns the most recently fetched value, if any. The value may be stale
+    /// if the resource has been invalidated and a refetch is in progress.
+    pub fn value(&self) -> Option<&Output> {
+        self.state.commit_at_root.value.as_deref()
+    }
+
+    /// Returns true if a future is currently running to fetch a value for the
+    /// resource.
+    pub fn is_fetching(&self) -> bool {
+        let state = &self.state.commit_at_root;
+        state.fetched != Some(state.generation)
+    }
+
+    /// Returns true if the resource has a value from a previous fetch and a
+    /// refetch is in progress.
+    pub fn is_stale(&self) -> bool {
+        self.value().is_some() && self.is_fetching()
+    }
+
+    /// Marks the resource's value as outdated, waking the runtime. The next
+    /// revision which requests this resource's key will spawn a new future to
+    /// refetch it, cancelling any that is still running. The current value is
+    /// still returned from [`Resource::value`] until the refetch completes.
+    #[track_caller]
+    pub fn invalidate(&self) {
+        self.state.update(|prev| {
+            Some(Fetch {
+                value: prev.value.clone(),
+                generation: prev.generation + 1,
+                fetched: prev.fetched,
+            })
+        });
+    }
+}
+
+impl<Output> Clone for Resource<Output> {
+    fn clone(&self) -> Self {
+        Self { state: self.state.clone() }
+    }
+}
+
+impl<Output> Debug for Resource<Output>
+where
+    Output: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Resource")
+            .field("value", &self.value())
+            .field("is_fetching", &self.is_fetching())
+            .finish()
+    }
+}
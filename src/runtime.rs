@@ -2,7 +2,9 @@
 //! embedding environments.
 
 mod context;
+mod provided;
 mod runloop;
+mod shutdown;
 mod var;
 
 use dyn_cache::local::SharedLocalCache;
@@ -12,14 +14,19 @@ use futures::{
 };
 use illicit::AsContext;
 use std::{
+    collections::HashMap,
     fmt::{Debug, Formatter, Result as FmtResult},
     rc::Rc,
     task::Waker,
 };
 
 pub(crate) use context::Context;
-pub use runloop::RunLoop;
-pub(crate) use var::Var;
+pub(crate) use provided::ConsumerLog;
+pub use runloop::{RunLoop, TimedOut};
+pub(crate) use shutdown::Registry;
+pub use shutdown::{Leak, Shutdown};
+pub use var::WakeReason;
+pub(crate) use var::{Rooted, Var, WakeLog};
 
 /// Revisions measure moxie's notion of time passing. Each `Runtime` increments
 /// its Revision on every iteration. `crate::Commit`s to state variables are
@@ -126,6 +133,11 @@ pub struct Runtime {
     revision: Revision,
     cache: SharedLocalCache,
     spawner: Spawner,
+    vars: Registry,
+    wakes: WakeLog,
+    wake_reasons: Vec<WakeReason>,
+    consumers: ConsumerLog,
+    consumers_at_last_revision: HashMap<topo::CallId, Vec<topo::CallId>>,
     wk: Waker,
 }
 
@@ -143,6 +155,11 @@ impl Runtime {
             spawner: Spawner(Rc::new(JunkSpawner)),
             revision: Revision(0),
             cache: SharedLocalCache::default(),
+            vars: Registry::default(),
+            wakes: WakeLog::default(),
+            wake_reasons: Vec::new(),
+            consumers: ConsumerLog::default(),
+            consumers_at_last_revision: HashMap::new(),
             wk: noop_waker(),
         }
     }
@@ -153,18 +170,179 @@ impl Runtime {
         self.revision
     }
 
+    /// Returns the state changes which woke the runtime before its most recent
+    /// revision, in the order they were first made. Repeated changes to the same
+    /// state variable from the same location are reported once. Changes made
+    /// during a revision are reported for the following one.
+    ///
+    /// Each reason is also emitted as a `tracing` event when it is recorded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use moxie::{runtime::RunLoop, state};
+    ///
+    /// let mut rt = RunLoop::new(|| state(|| 0u64));
+    /// let (_, key) = rt.run_once();
+    /// assert!(rt.wake_reasons().is_empty());
+    ///
+    /// key.set(1);
+    /// let line = line!() - 1;
+    /// rt.run_once();
+    ///
+    /// let reasons = rt.wake_reasons();
+    /// assert_eq!(reasons.len(), 1);
+    /// assert_eq!(reasons[0].id(), key.id());
+    /// assert_eq!(reasons[0].location().line(), line);
+    /// ```
+    pub fn wake_reasons(&self) -> &[WakeReason] {
+        &self.wake_reasons
+    }
+
+    /// Returns the callsites which read the state variable rooted at `provider`
+    /// with [`crate::use_provided`] during the most recent revision, in the
+    /// order they first read it. `provider` is the [`crate::Key::id`] of a
+    /// variable offered by [`crate::provide_state`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use moxie::{provide_state, runtime::RunLoop, use_provided, Key};
+    ///
+    /// #[topo::nested]
+    /// fn consumer() -> topo::CallId {
+    ///     let _: Key<u8> = use_provided().unwrap();
+    ///     topo::CallId::current()
+    /// }
+    ///
+    /// let mut rt = RunLoop::new(|| {
+    ///     provide_state(|| 0u8, || (use_provided::<u8>().unwrap().id(), consumer(), consumer()))
+    /// });
+    /// let (provider, first, second) = rt.run_once();
+    ///
+    /// assert_eq!(rt.consumers_of(&provider).len(), 3);
+    /// assert_eq!(&rt.consumers_of(&provider)[1..], [first, second]);
+    /// ```
+    pub fn consumers_of(&self, provider: &topo::CallId) -> &[topo::CallId] {
+        self.consumers_at_last_revision.get(provider).map_or(&[], Vec::as_slice)
+    }
+
+    /// Summarizes the values held in the runtime's cache, grouped by the type
+    /// of query which stored them. See [`dyn_cache::CacheStats`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use moxie::{runtime::RunLoop, state};
+    ///
+    /// let mut rt = RunLoop::new(|| state(|| 0u64));
+    /// rt.run_once();
+    ///
+    /// let stats = rt.cache_stats();
+    /// assert_eq!(stats.len(), 1, "one state variable is stored");
+    /// assert!(stats.namespaces()[0].output_type().contains("u64"));
+    /// ```
+    pub fn cache_stats(&self) -> dyn_cache::CacheStats {
+        self.cache.stats()
+    }
+
+    /// Passes each value held in the runtime's cache to `visitor`. The scopes
+    /// of values cached by moxie's functions are [`topo::CallId`]s. See
+    /// [`dyn_cache::CacheVisitor`].
+    pub fn visit_cache(&self, visitor: &mut impl dyn_cache::CacheVisitor) {
+        self.cache.visit(visitor);
+    }
+
+    /// Drops every value cached at `id`, including state variables and the
+    /// futures of loaders, so that they're re-initialized the next time they're
+    /// requested. Values cached by calls nested within `id` are kept. Wakes the
+    /// runtime so that it can run again with fresh values.
+    ///
+    /// Useful for "refresh" actions and for discarding values derived from
+    /// configuration which has been reloaded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use moxie::{once, runtime::RunLoop};
+    /// use std::cell::Cell;
+    ///
+    /// let num_loads = Cell::new(0);
+    /// let mut rt = RunLoop::new(|| {
+    ///     once(|| {
+    ///         num_loads.set(num_loads.get() + 1);
+    ///         topo::CallId::current()
+    ///     })
+    /// });
+    ///
+    /// let id = rt.run_once();
+    /// rt.run_once();
+    /// assert_eq!(num_loads.get(), 1);
+    ///
+    /// rt.invalidate(id);
+    /// rt.run_once();
+    /// assert_eq!(num_loads.get(), 2, "refreshed after invalidation");
+    /// ```
+    pub fn invalidate(&self, id: topo::CallId) {
+        self.cache.invalidate_where(|scope: &topo::CallId| *scope == id);
+        self.wk.wake_by_ref();
+    }
+
     /// Runs the root closure once with access to the runtime context,
     /// increments the runtime's `Revision`, and drops any cached values
     /// which were not marked alive.
     pub fn run_once<Out>(&mut self, op: impl FnOnce() -> Out) -> Out {
+        self.wake_reasons = self.wakes.take();
         self.revision.0 += 1;
 
         let ret = self.context_handle().offer(|| topo::call(op));
+        self.consumers_at_last_revision = self.consumers.take();
 
         self.cache.gc();
+        self.vars.prune();
         ret
     }
 
+    /// Tears down the runtime, dropping every value in its cache. Values are
+    /// dropped before any values they cached during their own initialization,
+    /// and dropping the guards of running loaders aborts their tasks.
+    ///
+    /// Returns a [`Shutdown`] report of any state variables which are still
+    /// held outside of the runtime.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures::{executor::LocalPool, future::pending};
+    /// use moxie::{
+    ///     load_once,
+    ///     runtime::{Leak, RunLoop},
+    ///     state,
+    /// };
+    ///
+    /// let mut rt = RunLoop::new(|| {
+    ///     let _ = load_once(|| pending::<()>());
+    ///     state(|| 0u8)
+    /// });
+    /// let mut pool = LocalPool::new();
+    /// rt.set_task_executor(pool.spawner());
+    ///
+    /// let (_, key) = rt.run_once();
+    /// pool.run_until_stalled();
+    ///
+    /// let report = rt.shutdown();
+    /// pool.run_until_stalled(); // let the aborted loader release its state
+    /// assert!(matches!(report.leaks().as_slice(), [Leak::Key { .. }]));
+    ///
+    /// drop(key);
+    /// assert!(report.is_clean());
+    /// ```
+    pub fn shutdown(self) -> Shutdown {
+        let report = self.vars.watch();
+        self.cache.clear();
+        report
+    }
+
     /// Sets the [`std::task::Waker`] which will be called when state variables
     /// receive commits. By default the runtime no-ops on a state change,
     /// which is probably the desired behavior if the embedding system will
@@ -180,7 +358,7 @@ impl Runtime {
 }
 
 #[derive(Clone)]
-struct Spawner(pub Rc<dyn LocalSpawn>);
+pub(crate) struct Spawner(pub Rc<dyn LocalSpawn>);
 
 impl Debug for Spawner {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
@@ -219,6 +397,54 @@ mod tests {
         assert!(illicit::get::<u8>().is_err());
     }
 
+    #[test]
+    fn run_until_reports_last_output() {
+        let mut drives = 0;
+        let mut rt = RunLoop::new(Revision::current);
+        let timed_out = rt.run_until_with(|_| false, 3, || drives += 1).unwrap_err();
+        assert_eq!(drives, 2, "no need to drive the executor after the last revision");
+        assert_eq!(timed_out.revisions(), 3);
+        assert_eq!(timed_out.last_output(), &Revision(3));
+        assert_eq!(
+            timed_out.to_string(),
+            "predicate not satisfied after 3 revisions, last output: r3"
+        );
+
+        assert_eq!(rt.run_until(|rev| rev.0 == 5, 3).unwrap(), Revision(5));
+        assert_eq!(rt.run_until(|_| false, 1).unwrap_err().revisions(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "run_until needs a limit of at least one revision")]
+    fn run_until_rejects_zero_limit() {
+        RunLoop::new(Revision::current).run_until(|_| true, 0).ok();
+    }
+
+    #[test]
+    fn repeated_wakes_coalesced() {
+        let mut rt = RunLoop::new(|| (crate::state(|| 0u32).1, crate::state(|| 0u32).1));
+        let (first, second) = rt.run_once();
+
+        for i in 1..1000 {
+            first.set(i);
+            second.set(i);
+        }
+        first.set(0);
+        rt.run_once();
+
+        let reasons = rt.wake_reasons();
+        assert_eq!(reasons.len(), 3, "one per variable and location");
+        assert_eq!(reasons[0].id(), first.id());
+        assert_eq!(reasons[1].id(), second.id());
+        assert_eq!(reasons[2].id(), first.id());
+    }
+
+    #[test]
+    fn keys_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<crate::Key<u32>>();
+    }
+
     #[test]
     fn tick_a_few_times() {
         let mut rt = RunLoop::new(Revision::current);
@@ -1,10 +1,16 @@
-use super::{Revision, Spawner, Var};
-use crate::{Commit, Key};
+use super::{ConsumerLog, Registry, Revision, Rooted, Spawner, Var, WakeLog};
+use crate::{
+    env::Tracked,
+    resource::{Fetch, InFlight},
+    Commit, Key, Resource,
+};
 use dyn_cache::local::SharedLocalCache;
 use futures::future::abortable;
 use std::{
     borrow::Borrow,
     future::Future,
+    hash::Hash,
+    rc::Rc,
     task::{Poll, Waker},
 };
 
@@ -16,6 +22,9 @@ pub(crate) struct Context {
     revision: Revision,
     pub cache: SharedLocalCache,
     spawner: Spawner,
+    vars: Registry,
+    wakes: WakeLog,
+    consumers: ConsumerLog,
     waker: Waker,
 }
 
@@ -25,22 +34,127 @@ impl Context {
         self.revision
     }
 
+    /// Spawns `task` on the runtime's executor.
+    ///
+    /// # Panics
+    ///
+    /// If the [`super::Runtime`] from which `self` was created did not have
+    /// a valid call to `set_task_executor`.
+    pub fn spawn(&self, task: impl Future<Output = ()> + 'static) {
+        self.spawner
+            .0
+            .spawn_local_obj(Box::pin(task).into())
+            .expect("that set_task_executor has been called");
+    }
+
+    /// Record that the current callsite read the provided state variable
+    /// rooted at `provider`.
+    pub fn consume(&self, provider: topo::CallId) {
+        self.consumers.record(provider, topo::CallId::current());
+    }
+
+    /// Caches the result of `init(arg)` at `id`, re-running it when `arg`
+    /// changes or when a value which `init` read with [`crate::env`] is no
+    /// longer equal to the one offered by the environment. Always runs `with`
+    /// on the stored `Output` before returning the result.
+    ///
+    /// Values read by `init` count as read by any cached value whose
+    /// initialization this is called from, as its output may depend on them.
+    pub fn cache_with<Arg, Input, Output, Ret>(
+        &self,
+        id: &topo::CallId,
+        arg: &Arg,
+        init: impl FnOnce(&Input) -> Output,
+        with: impl FnOnce(&Output) -> Ret,
+    ) -> Ret
+    where
+        Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+        Input: Borrow<Arg> + 'static,
+        Output: 'static,
+        Ret: 'static,
+    {
+        let (mut init, mut with) = (Some(init), Some(with));
+        loop {
+            let ret = self.cache.cache_with(
+                id,
+                arg,
+                |input| Tracked::init(|| init.take().expect("initializes at most once")(input)),
+                |tracked: &Tracked<Output>| tracked.current().map(|o| with.take().unwrap()(o)),
+            );
+            match ret {
+                Some(ret) => return ret,
+                // a value read by `init` has changed, re-initialize
+                None => self.cache.invalidate::<topo::CallId, Input, Tracked<Output>, _>(id),
+            };
+        }
+    }
+
+    /// Caches the results of `init(arg)` at `id` for up to `capacity` of the
+    /// most recently used values of `arg`, discarding all of them when a value
+    /// which the requested output's `init` read with [`crate::env`] is no
+    /// longer equal to the one offered by the environment. Always runs `with` on the stored `Output`
+    /// before returning the result.
+    pub fn cache_lru_with<Arg, Input, Output, Ret>(
+        &self,
+        id: &topo::CallId,
+        arg: &Arg,
+        capacity: usize,
+        init: impl FnOnce(&Input) -> Output,
+        with: impl FnOnce(&Output) -> Ret,
+    ) -> Ret
+    where
+        Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
+        Input: 'static,
+        Output: 'static,
+        Ret: 'static,
+    {
+        let (mut init, mut with) = (Some(init), Some(with));
+        loop {
+            let ret = self.cache.cache_lru_with(
+                id,
+                arg,
+                capacity,
+                |input| Tracked::init(|| init.take().expect("initializes at most once")(input)),
+                |tracked: &Tracked<Output>| tracked.current().map(|o| with.take().unwrap()(o)),
+            );
+            match ret {
+                Some(ret) => return ret,
+                // also discards the outputs retained for other inputs
+                None => self.cache.invalidate::<topo::CallId, Input, Tracked<Output>, _>(id),
+            };
+        }
+    }
+
     /// Load a [`crate::state::Var`] with the provided argument and initializer.
     /// Re-initializes the `Var` whenever `arg` changes.
-    pub fn cache_state<Arg, Input, Output>(
+    pub fn cache_state<Id, Scope, Arg, Input, Output>(
         &self,
-        id: &topo::CallId,
+        id: &Id,
         arg: &Arg,
         init: impl FnOnce(&Input) -> Output,
     ) -> (Commit<Output>, Key<Output>)
     where
+        Id: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+        Scope: Borrow<Id> + Eq + Hash + 'static,
         Arg: PartialEq<Input> + ToOwned<Owned = Input> + ?Sized,
         Input: Borrow<Arg> + 'static,
         Output: 'static,
     {
-        let var = self
-            .cache
-            .cache(id, arg, |arg| Var::new(topo::CallId::current(), self.waker.clone(), init(arg)));
+        let var = self.cache.cache_with(
+            id,
+            arg,
+            |arg| {
+                let var = Var::new(
+                    topo::CallId::current(),
+                    self.waker.clone(),
+                    self.wakes.clone(),
+                    init(arg),
+                );
+                self.vars.track(&var);
+                Rooted(var)
+            },
+            |rooted| rooted.0.clone(),
+        );
         Var::root(var)
     }
 
@@ -80,10 +194,7 @@ impl Context {
                     set_result.update(|_| Some(Poll::Ready(to_store)));
                 }
             };
-            self.spawner
-                .0
-                .spawn_local_obj(Box::pin(task).into())
-                .expect("that set_task_executor has been called");
+            self.spawn(task);
             scopeguard::guard(aborter, |a| a.abort())
         });
 
@@ -94,6 +205,50 @@ impl Context {
             Poll::Pending => Poll::Pending,
         }
     }
+
+    /// Load a value from the future returned by `fetch`, sharing a single
+    /// in-flight future and result between every caller which passes an equal
+    /// `key`. Spawns a new future whenever the resource is invalidated,
+    /// continuing to offer the previous result until the new one arrives.
+    /// Cancels the running future after any revision in which the `key` was
+    /// not requested.
+    ///
+    /// # Panics
+    ///
+    /// If the [`super::Runtime`] from which `self` was created did not have
+    /// a valid call to `set_task_executor`.
+    pub fn resource<Arg, Scope, Fut, Output>(
+        &self,
+        key: &Arg,
+        fetch: impl FnOnce(&Arg) -> Fut,
+    ) -> Resource<Output>
+    where
+        Arg: Eq + Hash + ToOwned<Owned = Scope> + ?Sized,
+        Scope: Borrow<Arg> + Eq + Hash + 'static,
+        Fut: Future<Output = Output> + 'static,
+        Output: 'static,
+    {
+        let (_, state): (_, Key<Fetch<Output>>) = self.cache_state(key, &(), |()| Fetch::default());
+        let state2 = state.clone();
+        self.cache.hold(key, &state2.commit_at_root.generation, |&generation| {
+            let (fut, aborter) = abortable(fetch(key));
+            let task = async move {
+                if let Ok(fetched) = fut.await {
+                    state.update(|prev| {
+                        Some(Fetch {
+                            value: Some(Rc::new(fetched)),
+                            generation: prev.generation,
+                            fetched: Some(generation),
+                        })
+                    });
+                }
+            };
+            self.spawn(task);
+            InFlight::<Output>::new(aborter)
+        });
+
+        Resource { state: state2 }
+    }
 }
 
 impl super::Runtime {
@@ -102,6 +257,9 @@ impl super::Runtime {
             revision: self.revision,
             spawner: self.spawner.clone(),
             cache: self.cache.clone(),
+            vars: self.vars.clone(),
+            wakes: self.wakes.clone(),
+            consumers: self.consumers.clone(),
             waker: self.wk.clone(),
         }
     }
@@ -0,0 +1,37 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+use topo::CallId;
+
+/// Records which callsites read each state variable offered by
+/// [`crate::provide_state`] during a revision.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ConsumerLog {
+    inner: Rc<RefCell<Consumers>>,
+}
+
+#[derive(Debug, Default)]
+struct Consumers {
+    /// the consumers of each provided variable in the order they first read it
+    by_provider: HashMap<CallId, Vec<CallId>>,
+    recorded: HashSet<(CallId, CallId)>,
+}
+
+impl ConsumerLog {
+    /// Record that the callsite `consumer` read the variable rooted at `provider`.
+    pub fn record(&self, provider: CallId, consumer: CallId) {
+        let mut consumers = self.inner.borrow_mut();
+        if consumers.recorded.insert((provider, consumer)) {
+            consumers.by_provider.entry(provider).or_default().push(consumer);
+        }
+    }
+
+    /// Returns the consumers recorded since the last call, indexed by provider.
+    pub fn take(&self) -> HashMap<CallId, Vec<CallId>> {
+        let mut consumers = self.inner.borrow_mut();
+        consumers.recorded.clear();
+        std::mem::take(&mut consumers.by_provider)
+    }
+}
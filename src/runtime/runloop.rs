@@ -1,9 +1,8 @@
-use super::{Revision, Runtime};
-use futures::{
-    stream::{Stream, StreamExt},
-    task::LocalSpawn,
-};
+use super::{Revision, Runtime, Shutdown, WakeReason};
+use futures::{future::poll_fn, stream::Stream, task::LocalSpawn};
 use std::{
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
     pin::Pin,
     task::{Context as FutContext, Poll, Waker},
 };
@@ -43,6 +42,36 @@ where
         self.inner.revision()
     }
 
+    /// Returns the state changes which woke the runtime before its most recent
+    /// revision. See [`Runtime::wake_reasons`].
+    pub fn wake_reasons(&self) -> &[WakeReason] {
+        self.inner.wake_reasons()
+    }
+
+    /// Returns the callsites which read the state variable rooted at `provider`
+    /// during the most recent revision. See [`Runtime::consumers_of`].
+    pub fn consumers_of(&self, provider: &topo::CallId) -> &[topo::CallId] {
+        self.inner.consumers_of(provider)
+    }
+
+    /// Summarizes the values held in the runtime's cache. See
+    /// [`Runtime::cache_stats`].
+    pub fn cache_stats(&self) -> dyn_cache::CacheStats {
+        self.inner.cache_stats()
+    }
+
+    /// Passes each value held in the runtime's cache to `visitor`. See
+    /// [`Runtime::visit_cache`].
+    pub fn visit_cache(&self, visitor: &mut impl dyn_cache::CacheVisitor) {
+        self.inner.visit_cache(visitor);
+    }
+
+    /// Drops every value cached at `id` so that they're re-initialized. See
+    /// [`Runtime::invalidate`].
+    pub fn invalidate(&self, id: topo::CallId) {
+        self.inner.invalidate(id);
+    }
+
     /// Sets the [`std::task::Waker`] which will be called when state variables
     /// change.
     pub fn set_state_change_waker(&mut self, wk: Waker) {
@@ -60,13 +89,142 @@ where
         self.inner.run_once(&mut self.root)
     }
 
+    /// Run revisions until `pred` returns true for the root function's output,
+    /// returning that output, or [`TimedOut`] with the last output if `pred` is
+    /// still false after `limit` revisions.
+    ///
+    /// Nothing else runs in between revisions, so this is best suited to roots
+    /// whose state is updated synchronously. See [`RunLoop::run_until_with`] to
+    /// make progress on spawned tasks in between revisions.
+    ///
+    /// # Panics
+    ///
+    /// If `limit` is 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use moxie::{runtime::RunLoop, state};
+    ///
+    /// let mut rt = RunLoop::new(|| {
+    ///     let (count, incrementer) = state(|| 0u8);
+    ///     incrementer.update(|n| Some(n + 1));
+    ///     *count
+    /// });
+    ///
+    /// assert_eq!(rt.run_until(|count| *count == 3, 5).unwrap(), 3);
+    /// assert_eq!(rt.run_until(|count| *count == 0, 2).unwrap_err().revisions(), 2);
+    /// ```
+    pub fn run_until(
+        &mut self,
+        pred: impl FnMut(&Out) -> bool,
+        limit: u64,
+    ) -> Result<Out, TimedOut<Out>> {
+        self.run_until_with(pred, limit, || ())
+    }
+
+    /// Like [`RunLoop::run_until`], but calls `drive` after each revision whose
+    /// output didn't satisfy `pred`, allowing the caller to make progress on
+    /// any spawned tasks before the next revision.
+    ///
+    /// # Panics
+    ///
+    /// If `limit` is 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures::{channel::oneshot, executor::LocalPool};
+    /// use moxie::{load_once, runtime::RunLoop};
+    /// use std::task::Poll;
+    ///
+    /// let (send, recv) = oneshot::channel();
+    /// let mut recv = Some(recv);
+    /// let mut rt = RunLoop::new(move || {
+    ///     let recv = recv.take();
+    ///     load_once(|| async move { recv.unwrap().await.unwrap() })
+    /// });
+    /// let mut pool = LocalPool::new();
+    /// rt.set_task_executor(pool.spawner());
+    ///
+    /// let timed_out =
+    ///     rt.run_until_with(|out| out.is_ready(), 3, || pool.run_until_stalled()).unwrap_err();
+    /// assert_eq!(timed_out.revisions(), 3);
+    /// assert_eq!(timed_out.last_output(), &Poll::Pending);
+    ///
+    /// send.send(5u8).unwrap();
+    /// let ready = rt.run_until_with(|out| out.is_ready(), 3, || pool.run_until_stalled());
+    /// assert_eq!(ready.unwrap(), Poll::Ready(5));
+    /// ```
+    pub fn run_until_with(
+        &mut self,
+        mut pred: impl FnMut(&Out) -> bool,
+        limit: u64,
+        mut drive: impl FnMut(),
+    ) -> Result<Out, TimedOut<Out>> {
+        assert!(limit > 0, "run_until needs a limit of at least one revision");
+        let mut revisions = 0;
+        loop {
+            let out = self.run_once();
+            revisions += 1;
+            if pred(&out) {
+                return Ok(out);
+            } else if revisions >= limit {
+                return Err(TimedOut { last: out, revisions });
+            }
+            drive();
+        }
+    }
+
+    /// Run revisions whenever state variables change until `pred` returns true
+    /// for the root function's output, returning that output. The returned
+    /// future must be polled by an executor which also runs the runtime's
+    /// tasks, so that they can make progress while it waits.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures::{channel::oneshot, executor::LocalPool};
+    /// use moxie::{load_once, runtime::RunLoop};
+    /// use std::task::Poll;
+    ///
+    /// let (send, recv) = oneshot::channel();
+    /// let mut recv = Some(recv);
+    /// let mut rt = RunLoop::new(move || {
+    ///     let recv = recv.take();
+    ///     load_once(|| async move { recv.unwrap().await.unwrap() })
+    /// });
+    /// let mut pool = LocalPool::new();
+    /// rt.set_task_executor(pool.spawner());
+    ///
+    /// send.send(5u8).unwrap();
+    /// assert_eq!(pool.run_until(rt.wait_for(|out| out.is_ready())), Poll::Ready(5));
+    /// ```
+    pub async fn wait_for(&mut self, mut pred: impl FnMut(&Out) -> bool) -> Out {
+        poll_fn(|cx| match Pin::new(&mut *self).poll_next(cx) {
+            Poll::Ready(Some((_, out))) if pred(&out) => Poll::Ready(out),
+            // the runtime wakes this task when it has new state to render
+            _ => Poll::Pending,
+        })
+        .await
+    }
+
     /// Poll this runtime without exiting. Discards any value returned from the
     /// root function. The future yields in between revisions and is woken on
     /// state changes.
     pub async fn run_on_state_changes(mut self) {
-        loop {
-            self.next().await;
-        }
+        poll_fn(|cx| {
+            let _ = Pin::new(&mut self).poll_next(cx);
+            // the runtime wakes this task when it has new state to render
+            Poll::<()>::Pending
+        })
+        .await
+    }
+
+    /// Tears down the runtime, dropping the root function afterwards. See
+    /// [`Runtime::shutdown`].
+    pub fn shutdown(self) -> Shutdown {
+        self.inner.shutdown()
     }
 
     /// Unbinds the runtime from its current root function, returning both.
@@ -90,3 +248,42 @@ where
         Poll::Ready(Some((this.inner.revision, out)))
     }
 }
+
+/// The error returned by [`RunLoop::run_until`] and [`RunLoop::run_until_with`] when its predicate wasn't
+/// satisfied within the given number of revisions.
+#[derive(Debug)]
+pub struct TimedOut<Out> {
+    last: Out,
+    revisions: u64,
+}
+
+impl<Out> TimedOut<Out> {
+    /// Returns the output of the last revision which was run.
+    pub fn last_output(&self) -> &Out {
+        &self.last
+    }
+
+    /// Returns the output of the last revision which was run.
+    pub fn into_last_output(self) -> Out {
+        self.last
+    }
+
+    /// Returns the number of revisions which were run.
+    pub fn revisions(&self) -> u64 {
+        self.revisions
+    }
+}
+
+impl<Out> Display for TimedOut<Out>
+where
+    Out: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_fmt(format_args!(
+            "predicate not satisfied after {} revisions, last output: {:?}",
+            self.revisions, &self.last,
+        ))
+    }
+}
+
+impl<Out> Error for TimedOut<Out> where Out: Debug {}
@@ -0,0 +1,128 @@
+use super::Var;
+use parking_lot::Mutex;
+use std::{
+    any::{type_name, Any},
+    cell::RefCell,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    rc::Rc,
+    sync::{Arc, Weak},
+};
+use topo::CallId;
+
+/// Weak references to every state variable created by a [`super::Runtime`]
+/// which is still live, used to find variables that outlive it.
+#[derive(Clone, Default)]
+pub(crate) struct Registry {
+    vars: Rc<RefCell<Vec<Box<dyn Tracked>>>>,
+}
+
+impl Registry {
+    /// Track `var` until it is dropped.
+    pub fn track<State: 'static>(&self, var: &Arc<Mutex<Var<State>>>) {
+        self.vars.borrow_mut().push(Box::new(Arc::downgrade(var)));
+    }
+
+    /// Stop tracking any variables which have been dropped.
+    pub fn prune(&self) {
+        self.vars.borrow_mut().retain(|var| var.is_live());
+    }
+
+    /// Capture weak references to all live variables and their latest commits.
+    pub fn watch(&self) -> Shutdown {
+        Shutdown { watched: self.vars.borrow().iter().filter_map(|var| var.watch()).collect() }
+    }
+}
+
+impl Debug for Registry {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Registry").field("vars", &self.vars.borrow().len()).finish()
+    }
+}
+
+/// A state variable tracked by a [`Registry`], with its type erased.
+trait Tracked {
+    fn is_live(&self) -> bool;
+    fn watch(&self) -> Option<Watched>;
+}
+
+impl<State: 'static> Tracked for Weak<Mutex<Var<State>>> {
+    fn is_live(&self) -> bool {
+        self.strong_count() > 0
+    }
+
+    fn watch(&self) -> Option<Watched> {
+        let var = self.upgrade()?;
+        let committed = var.lock().committed().clone();
+        let (key, commit) = (self.clone(), Arc::downgrade(&committed.inner));
+        Some(Watched { id: committed.id, state: type_name::<State>(), key, commit })
+    }
+}
+
+struct Watched {
+    id: CallId,
+    state: &'static str,
+    key: Weak<dyn Any>,
+    commit: Weak<dyn Any>,
+}
+
+/// A state variable which outlived the [`super::Runtime`] that created it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Leak {
+    /// A [`crate::Key`] to the variable is still held.
+    Key {
+        /// The callsite which created the variable.
+        id: CallId,
+        /// The type name of the variable's state.
+        state: &'static str,
+    },
+    /// The variable has been dropped but a [`crate::Commit`] of its latest
+    /// value is still held.
+    Commit {
+        /// The callsite which created the variable.
+        id: CallId,
+        /// The type name of the variable's state.
+        state: &'static str,
+    },
+}
+
+/// The result of [`super::Runtime::shutdown`], which reports any state
+/// variables still held outside of the runtime.
+///
+/// Leaks are checked each time [`Shutdown::leaks`] is called. Tasks spawned by
+/// the runtime are aborted during shutdown but only release their state
+/// variables once the executor polls them again, so callers may want to drive
+/// their executor before checking for leaks.
+pub struct Shutdown {
+    watched: Vec<Watched>,
+}
+
+impl Shutdown {
+    /// Returns all state variables whose [`crate::Key`] or latest
+    /// [`crate::Commit`] are still held.
+    pub fn leaks(&self) -> Vec<Leak> {
+        self.watched
+            .iter()
+            .filter_map(|w| {
+                let (id, state) = (w.id, w.state);
+                if w.key.strong_count() > 0 {
+                    Some(Leak::Key { id, state })
+                } else if w.commit.strong_count() > 0 {
+                    Some(Leak::Commit { id, state })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns true if no state variables have leaked.
+    pub fn is_clean(&self) -> bool {
+        self.leaks().is_empty()
+    }
+}
+
+impl Debug for Shutdown {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Shutdown").field("leaks", &self.leaks()).finish()
+    }
+}
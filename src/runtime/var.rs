@@ -1,20 +1,27 @@
+use super::Revision;
 use crate::{Commit, Key};
 use parking_lot::Mutex;
-use std::{sync::Arc, task::Waker};
+use std::{collections::HashSet, panic::Location, sync::Arc, task::Waker};
+use topo::CallId;
 
 /// The underlying container of state variables. Vends copies of the latest
 /// [`Commit`] for [`Key`]s.
 pub(crate) struct Var<State> {
     current: Commit<State>,
-    id: topo::CallId,
+    id: CallId,
     pending: Option<Commit<State>>,
+    /// the revision in which the variable was last rooted, if it's still held by its runtime
+    rooted: Option<Revision>,
     waker: Waker,
+    wakes: WakeLog,
 }
 
 impl<State> Var<State> {
-    pub fn new(id: topo::CallId, waker: Waker, inner: State) -> Arc<Mutex<Self>> {
-        let current = Commit { id, inner: Arc::new(inner) };
-        Arc::new(Mutex::new(Var { id, current, waker, pending: None }))
+    pub fn new(id: CallId, waker: Waker, wakes: WakeLog, inner: State) -> Arc<Mutex<Self>> {
+        let revision = Revision::current();
+        let current = Commit { id, inner: Arc::new(inner), revision };
+        let rooted = Some(revision);
+        Arc::new(Mutex::new(Var { id, current, rooted, waker, wakes, pending: None }))
     }
 
     /// Attach this `Var` to its callsite, performing any pending commit and
@@ -22,7 +29,12 @@ impl<State> Var<State> {
     pub fn root(var: Arc<Mutex<Self>>) -> (Commit<State>, Key<State>) {
         let (id, commit_at_root) = {
             let mut var = var.lock();
-            if let Some(pending) = var.pending.take() {
+            let revision = Revision::current();
+            if var.rooted.is_some() {
+                var.rooted = Some(revision);
+            }
+            if let Some(mut pending) = var.pending.take() {
+                pending.revision = revision;
                 var.current = pending;
             }
             (var.id, var.current.clone())
@@ -31,16 +43,118 @@ impl<State> Var<State> {
         (commit_at_root.clone(), Key { id, commit_at_root, var })
     }
 
+    /// Returns the revision in which the variable was last rooted, or `None` if
+    /// its runtime no longer holds it.
+    pub fn last_rooted(&self) -> Option<Revision> {
+        self.rooted
+    }
+
     /// Returns a reference to the latest value, pending or committed.
     pub fn latest(&self) -> &State {
+        self.latest_commit()
+    }
+
+    /// Returns the latest commit which has been rooted, ignoring any pending
+    /// commit.
+    pub fn committed(&self) -> &Commit<State> {
+        &self.current
+    }
+
+    /// Returns the latest commit, pending or committed.
+    pub fn latest_commit(&self) -> &Commit<State> {
         self.pending.as_ref().unwrap_or(&self.current)
     }
 
     /// Initiate a commit to the state variable. The commit will actually
     /// complete asynchronously when the state variable is next rooted in a
     /// topological function, flushing the pending commit.
+    #[track_caller]
     pub fn enqueue_commit(&mut self, state: State) {
-        self.pending = Some(Commit { inner: Arc::new(state), id: self.id });
+        self.enqueue_commit_from(state, Location::caller());
+    }
+
+    /// Initiate a commit to the state variable on behalf of code at `location`.
+    pub fn enqueue_commit_from(&mut self, state: State, location: &'static Location<'static>) {
+        // the revision is assigned when the commit is rooted
+        let revision = Revision::default();
+        self.enqueue(Commit { inner: Arc::new(state), id: self.id, revision }, location);
+    }
+
+    /// Initiate a commit of a previously-created `Commit`, e.g. to revert to an
+    /// earlier value.
+    pub fn enqueue(&mut self, commit: Commit<State>, location: &'static Location<'static>) {
+        self.pending = Some(commit);
+        self.wake(location);
+    }
+
+    /// Wake the runtime, recording `location` as the reason.
+    pub fn wake(&self, location: &'static Location<'static>) {
+        self.wakes.record(WakeReason { id: self.id, location });
         self.waker.wake_by_ref();
     }
 }
+
+/// The reference to a [`Var`] held by its runtime's cache, which marks the
+/// variable as no longer live when the cache drops it.
+pub(crate) struct Rooted<State>(pub Arc<Mutex<Var<State>>>);
+
+impl<State> Drop for Rooted<State> {
+    fn drop(&mut self) {
+        self.0.lock().rooted = None;
+    }
+}
+
+/// A state change which woke a [`super::Runtime`], recorded when code such as
+/// [`Key::update`] enqueues a commit to a state variable.
+///
+/// See [`super::Runtime::wake_reasons`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct WakeReason {
+    id: CallId,
+    location: &'static Location<'static>,
+}
+
+impl WakeReason {
+    /// Returns the callsite which created the state variable.
+    pub fn id(&self) -> CallId {
+        self.id
+    }
+
+    /// Returns the source location of the code which changed the state
+    /// variable.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+}
+
+/// Collects the reasons a runtime was woken since its last revision. Repeated
+/// wakes from the same location for the same variable are only recorded once,
+/// so the log is bounded by the number of variables and the code updating them.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct WakeLog {
+    inner: Arc<Mutex<Wakes>>,
+}
+
+#[derive(Debug, Default)]
+struct Wakes {
+    /// each distinct reason in the order it was first recorded
+    reasons: Vec<WakeReason>,
+    recorded: HashSet<WakeReason>,
+}
+
+impl WakeLog {
+    fn record(&self, reason: WakeReason) {
+        tracing::debug!(id = ?reason.id, location = %reason.location, "state change woke runtime");
+        let mut wakes = self.inner.lock();
+        if wakes.recorded.insert(reason.clone()) {
+            wakes.reasons.push(reason);
+        }
+    }
+
+    /// Returns all reasons recorded since the last call.
+    pub fn take(&self) -> Vec<WakeReason> {
+        let mut wakes = self.inner.lock();
+        wakes.recorded.clear();
+        std::mem::take(&mut wakes.reasons)
+    }
+}
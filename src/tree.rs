@@ -0,0 +1,448 @@
+//! Incremental maintenance of retained trees like the DOM, native widget
+//! hierarchies, or scene graphs.
+//!
+//! Many embeddings render to a tree of nodes which persists between
+//! [`crate::runtime::Revision`]s. Rather than rebuilding that tree each
+//! revision, an embedding can describe its nodes with a [`TreeBackend`] and
+//! wrap each one in a [`Retained`] node, which only issues the mutations needed
+//! to bring the tree in line with the current revision:
+//!
+//! * children which are already attached in the declared position are left
+//!   alone, others are inserted or replace the previous occupant
+//! * children left over from previous revisions are removed
+//! * properties are set when their value changes and removed when they're no
+//!   longer declared
+//!
+//! # Example
+//!
+//! ```
+//! use moxie::{
+//!     runtime::RunLoop,
+//!     tree::{Retained, TreeBackend},
+//! };
+//! use std::{cell::RefCell, rc::Rc};
+//!
+//! /// A tree of labels which records the number of mutations made.
+//! #[derive(Clone, Debug, Default)]
+//! struct Labels {
+//!     mutations: Rc<RefCell<u32>>,
+//! }
+//!
+//! #[derive(Debug)]
+//! struct Label {
+//!     text: RefCell<Option<String>>,
+//!     children: RefCell<Vec<Rc<Label>>>,
+//! }
+//!
+//! #[derive(Clone, Debug)]
+//! struct Handle(Rc<Label>);
+//!
+//! impl PartialEq for Handle {
+//!     fn eq(&self, other: &Self) -> bool {
+//!         Rc::ptr_eq(&self.0, &other.0)
+//!     }
+//! }
+//!
+//! impl Labels {
+//!     fn siblings_of(&self, parent: &Handle) -> Vec<Handle> {
+//!         parent.0.children.borrow().iter().cloned().map(Handle).collect()
+//!     }
+//!
+//!     fn mutate(&self, parent: &Handle, op: impl FnOnce(&mut Vec<Rc<Label>>)) {
+//!         *self.mutations.borrow_mut() += 1;
+//!         op(&mut parent.0.children.borrow_mut());
+//!     }
+//! }
+//!
+//! impl TreeBackend for Labels {
+//!     type Kind = ();
+//!     type Node = Handle;
+//!     type Prop = str;
+//!
+//!     fn create(&self, _: &()) -> Handle {
+//!         Handle(Rc::new(Label { text: RefCell::new(None), children: Default::default() }))
+//!     }
+//!
+//!     fn first_child(&self, parent: &Handle) -> Option<Handle> {
+//!         self.siblings_of(parent).first().cloned()
+//!     }
+//!
+//!     fn next_sibling(&self, parent: &Handle, node: &Handle) -> Option<Handle> {
+//!         let siblings = self.siblings_of(parent);
+//!         let index = siblings.iter().position(|s| s == node)?;
+//!         siblings.get(index + 1).cloned()
+//!     }
+//!
+//!     fn insert(&self, parent: &Handle, child: &Handle) {
+//!         self.mutate(parent, |c| c.push(child.0.clone()));
+//!     }
+//!
+//!     fn replace(&self, parent: &Handle, new: &Handle, existing: &Handle) {
+//!         self.mutate(parent, |c| {
+//!             c.retain(|s| !Rc::ptr_eq(s, &new.0));
+//!             let index = c.iter().position(|s| Rc::ptr_eq(s, &existing.0)).unwrap();
+//!             c[index] = new.0.clone();
+//!         });
+//!     }
+//!
+//!     fn remove(&self, parent: &Handle, child: &Handle) {
+//!         self.mutate(parent, |c| c.retain(|s| !Rc::ptr_eq(s, &child.0)));
+//!     }
+//!
+//!     fn set_prop(&self, node: &Handle, _name: &str, value: Option<&str>) {
+//!         *self.mutations.borrow_mut() += 1;
+//!         *node.0.text.borrow_mut() = value.map(ToOwned::to_owned);
+//!     }
+//! }
+//!
+//! let labels = Labels::default();
+//! let backend = labels.clone();
+//! let root = labels.create(&());
+//! let parent = root.clone();
+//! let mut rt = RunLoop::new(move || {
+//!     let parent = Retained::new(backend.clone(), parent.clone());
+//!     for text in &["hello", "world"] {
+//!         let label = Retained::create(backend.clone(), &());
+//!         label.set_prop("text", *text);
+//!         parent.ensure_child_attached(label.node());
+//!     }
+//!     parent.remove_trailing_children();
+//! });
+//!
+//! rt.run_once();
+//! assert_eq!(root.0.children.borrow().len(), 2);
+//! assert_eq!(*labels.mutations.borrow(), 4, "two children inserted, two props set");
+//!
+//! rt.run_once();
+//! assert_eq!(*labels.mutations.borrow(), 4, "nothing changed");
+//! ```
+
+use std::{
+    borrow::Borrow,
+    cell::Cell,
+    fmt::{Debug, Formatter, Result as FmtResult},
+};
+use topo::CallId;
+
+/// The operations needed to incrementally maintain a retained tree of nodes.
+///
+/// Implementors are usually cheap handles to the tree itself, they're cloned
+/// into each [`Retained`] node.
+pub trait TreeBackend: Clone + 'static {
+    /// A handle to a node in the tree. Two handles must compare equal if they
+    /// refer to the same node.
+    type Node: Clone + PartialEq + 'static;
+
+    /// A description of a node to create, like an element's tag name.
+    type Kind: Clone + PartialEq + 'static;
+
+    /// The type of values assigned to node properties.
+    type Prop: PartialEq<<Self::Prop as ToOwned>::Owned> + ToOwned + ?Sized + 'static;
+
+    /// Creates a new node which isn't yet attached to the tree.
+    fn create(&self, kind: &Self::Kind) -> Self::Node;
+
+    /// Returns the first child of `parent`, if any.
+    fn first_child(&self, parent: &Self::Node) -> Option<Self::Node>;
+
+    /// Returns the child of `parent` which follows `node`, if any.
+    fn next_sibling(&self, parent: &Self::Node, node: &Self::Node) -> Option<Self::Node>;
+
+    /// Attaches `child` to `parent` after its existing children.
+    fn insert(&self, parent: &Self::Node, child: &Self::Node);
+
+    /// Attaches `new` to `parent` in place of its `existing` child.
+    fn replace(&self, parent: &Self::Node, new: &Self::Node, existing: &Self::Node);
+
+    /// Detaches `child` from `parent`.
+    fn remove(&self, parent: &Self::Node, child: &Self::Node);
+
+    /// Sets the property `name` of `node` to `value`, or removes it if `value`
+    /// is `None`.
+    fn set_prop(&self, node: &Self::Node, name: &str, value: Option<&Self::Prop>);
+}
+
+/// A topologically-nested "incremental smart pointer" for a node in a retained
+/// tree.
+///
+/// A new `Retained` is created for each node in every revision. During the
+/// revision its children are declared in order with
+/// [`Retained::ensure_child_attached`] and any leftovers are removed with
+/// [`Retained::remove_trailing_children`].
+pub struct Retained<B: TreeBackend> {
+    backend: B,
+    id: CallId,
+    last_child: Cell<Option<B::Node>>,
+    node: B::Node,
+}
+
+impl<B: TreeBackend> Retained<B> {
+    /// Wraps an existing `node` of the tree, usually the root to which an
+    /// embedding attaches its output.
+    #[topo::nested]
+    pub fn new(backend: B, node: B::Node) -> Self {
+        Self { backend, node, last_child: Cell::new(None), id: CallId::current() }
+    }
+
+    /// Creates a node of the given `kind`, reusing the node created at this
+    /// callsite in the previous revision if it was created with the same
+    /// `kind`.
+    #[topo::nested]
+    pub fn create(backend: B, kind: &B::Kind) -> Self {
+        let node = crate::cache(kind, |kind| backend.create(kind));
+        Self::new(backend, node)
+    }
+
+    /// Returns the wrapped node.
+    pub fn node(&self) -> &B::Node {
+        &self.node
+    }
+
+    /// Returns the backend used to mutate the tree.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Sets the property `name` to `value` if it differs from the value set in
+    /// the previous revision. The property is removed at the end of the first
+    /// revision in which it isn't set.
+    #[topo::nested(slot = "&(self.id, name)")]
+    pub fn set_prop(&self, name: &'static str, value: &B::Prop)
+    where
+        <B::Prop as ToOwned>::Owned: Borrow<B::Prop> + 'static,
+    {
+        let mut should_set = false;
+        crate::cache_with(
+            value,
+            |_| {
+                // when this isn't the first time the property is being set for this node,
+                // this closure executes while the previous property's guard is still live.
+                // if we actually set the property here, it will be removed when this closure
+                // exits which we definitely don't want. easiest fix is to set the property
+                // after our hypothetical cleanup has completed
+                should_set = true;
+                PropGuard { backend: self.backend.clone(), node: self.node.clone(), name }
+            },
+            |_| {},
+        );
+
+        if should_set {
+            self.backend.set_prop(&self.node, name, Some(value));
+        }
+    }
+
+    /// Ensures that `new_child` is attached immediately after the child
+    /// declared before it in this revision, or as the first child if it's the
+    /// first to be declared.
+    pub fn ensure_child_attached(&self, new_child: &B::Node) {
+        let prev_sibling = self.last_child.replace(Some(new_child.clone()));
+
+        let existing = match prev_sibling {
+            None => self.backend.first_child(&self.node),
+            Some(prev) => self.backend.next_sibling(&self.node, &prev),
+        };
+
+        match existing {
+            Some(existing) if existing != *new_child => {
+                self.backend.replace(&self.node, new_child, &existing)
+            }
+            Some(_) => (),
+            None => self.backend.insert(&self.node, new_child),
+        }
+    }
+
+    /// Removes any children which follow the last child declared with
+    /// [`Retained::ensure_child_attached`] in this revision, or all children if
+    /// none were declared.
+    pub fn remove_trailing_children(&self) {
+        let last_desired_child = self.last_child.replace(None);
+
+        // if there weren't any children declared this revision, we need to
+        // make sure we clean up any from the last revision
+        let mut next_to_remove = if let Some(c) = last_desired_child {
+            // put back the last node we found this revision so this can be called multiple
+            // times
+            let next = self.backend.next_sibling(&self.node, &c);
+            self.last_child.set(Some(c));
+            next
+        } else {
+            self.backend.first_child(&self.node)
+        };
+
+        while let Some(to_remove) = next_to_remove {
+            next_to_remove = self.backend.next_sibling(&self.node, &to_remove);
+            self.backend.remove(&self.node, &to_remove);
+        }
+    }
+}
+
+impl<B: TreeBackend> Clone for Retained<B> {
+    /// Clones wrap the same node, so that an element can be cached, for
+    /// example with [`crate::cache_map`], and attached again in later
+    /// revisions.
+    fn clone(&self) -> Self {
+        let last_child = self.last_child.take();
+        self.last_child.set(last_child.clone());
+        Self {
+            backend: self.backend.clone(),
+            id: self.id,
+            last_child: Cell::new(last_child),
+            node: self.node.clone(),
+        }
+    }
+}
+
+impl<B> Debug for Retained<B>
+where
+    B: TreeBackend,
+    B::Node: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Retained").field("node", &self.node).finish()
+    }
+}
+
+/// Removes a property from a node when the property is no longer set.
+struct PropGuard<B: TreeBackend> {
+    backend: B,
+    node: B::Node,
+    name: &'static str,
+}
+
+impl<B: TreeBackend> Drop for PropGuard<B> {
+    fn drop(&mut self) {
+        self.backend.set_prop(&self.node, self.name, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Retained, TreeBackend};
+    use crate::{runtime::RunLoop, state};
+    use std::{cell::RefCell, rc::Rc};
+
+    /// An arena of nodes, each with a name and a list of children, which logs
+    /// every mutation.
+    #[derive(Clone, Default)]
+    struct Arena {
+        names: Rc<RefCell<Vec<&'static str>>>,
+        props: Rc<RefCell<Vec<Option<String>>>>,
+        children: Rc<RefCell<Vec<Vec<usize>>>>,
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Arena {
+        fn siblings(&self, parent: usize) -> Vec<usize> {
+            self.children.borrow()[parent].clone()
+        }
+
+        fn log(&self, op: &str, nodes: &[usize]) {
+            let names = self.names.borrow();
+            let nodes: Vec<_> = nodes.iter().map(|n| names[*n]).collect();
+            self.log.borrow_mut().push(format!("{} {}", op, nodes.join(" ")));
+        }
+
+        fn take_log(&self) -> Vec<String> {
+            self.log.borrow_mut().drain(..).collect()
+        }
+
+        fn render(&self, node: usize) -> String {
+            let children: Vec<_> =
+                self.siblings(node).into_iter().map(|c| self.render(c)).collect();
+            let prop = self.props.borrow()[node].clone().unwrap_or_default();
+            format!("{}{}({})", self.names.borrow()[node], prop, children.join(","))
+        }
+    }
+
+    impl TreeBackend for Arena {
+        type Kind = &'static str;
+        type Node = usize;
+        type Prop = str;
+
+        fn create(&self, kind: &&'static str) -> usize {
+            self.names.borrow_mut().push(kind);
+            self.props.borrow_mut().push(None);
+            self.children.borrow_mut().push(vec![]);
+            self.names.borrow().len() - 1
+        }
+
+        fn first_child(&self, parent: &usize) -> Option<usize> {
+            self.siblings(*parent).first().copied()
+        }
+
+        fn next_sibling(&self, parent: &usize, node: &usize) -> Option<usize> {
+            let siblings = self.siblings(*parent);
+            let index = siblings.iter().position(|s| s == node)?;
+            siblings.get(index + 1).copied()
+        }
+
+        fn insert(&self, parent: &usize, child: &usize) {
+            self.log("insert", &[*parent, *child]);
+            self.children.borrow_mut()[*parent].push(*child);
+        }
+
+        fn replace(&self, parent: &usize, new: &usize, existing: &usize) {
+            self.log("replace", &[*parent, *new, *existing]);
+            let mut children = self.children.borrow_mut();
+            // like the DOM, attaching a node detaches it from its previous position
+            children[*parent].retain(|c| c != new);
+            let index = children[*parent].iter().position(|c| c == existing).unwrap();
+            children[*parent][index] = *new;
+        }
+
+        fn remove(&self, parent: &usize, child: &usize) {
+            self.log("remove", &[*parent, *child]);
+            self.children.borrow_mut()[*parent].retain(|c| c != child);
+        }
+
+        fn set_prop(&self, node: &usize, name: &str, value: Option<&str>) {
+            self.log(&format!("set {}={:?}", name, value), &[*node]);
+            self.props.borrow_mut()[*node] = value.map(|v| format!("[{}]", v));
+        }
+    }
+
+    #[test]
+    fn reconciles_children_and_props() {
+        let arena = Arena::default();
+        let root = arena.create(&"root");
+        let backend = arena.clone();
+        let mut rt = RunLoop::new(move || {
+            let (kinds, set_kinds) = state(|| vec!["a", "b", "c"]);
+            let (title, set_title) = state(|| Some("hi"));
+
+            let parent = Retained::new(backend.clone(), root);
+            if let Some(title) = *title {
+                parent.set_prop("title", title);
+            }
+            for kind in kinds.iter() {
+                topo::call_in_slot(kind, || {
+                    let child = Retained::create(backend.clone(), kind);
+                    parent.ensure_child_attached(child.node());
+                });
+            }
+            parent.remove_trailing_children();
+            (set_kinds, set_title)
+        });
+
+        let (set_kinds, set_title) = rt.run_once();
+        assert_eq!(arena.render(root), "root[hi](a(),b(),c())");
+        assert_eq!(
+            arena.take_log(),
+            [r#"set title=Some("hi") root"#, "insert root a", "insert root b", "insert root c"]
+        );
+
+        rt.run_once();
+        assert!(arena.take_log().is_empty(), "no mutations without changes");
+
+        set_kinds.set(vec!["a", "c"]);
+        rt.run_once();
+        assert_eq!(arena.render(root), "root[hi](a(),c())");
+        assert_eq!(arena.take_log(), ["replace root c b"]);
+
+        set_title.set(None);
+        set_kinds.set(vec![]);
+        rt.run_once();
+        assert_eq!(arena.render(root), "root()");
+        assert_eq!(arena.take_log(), ["remove root a", "remove root c", "set title=None root"]);
+    }
+}
@@ -0,0 +1,80 @@
+#[macro_use]
+extern crate criterion;
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+
+fn wide_counted(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wide_counted");
+    for width in &[100u32, 1_000, 10_000] {
+        group.throughput(Throughput::Elements(u64::from(*width)));
+        group.bench_with_input(BenchmarkId::from_parameter(width), width, |b, width| {
+            b.iter(|| {
+                topo::root(|| {
+                    for _ in 0..*width {
+                        topo::call(|| ());
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+fn wide_slotted(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wide_slotted");
+    for width in &[100u32, 1_000, 10_000] {
+        group.throughput(Throughput::Elements(u64::from(*width)));
+        group.bench_with_input(BenchmarkId::from_parameter(width), width, |b, width| {
+            b.iter(|| {
+                topo::root(|| {
+                    for i in 0..*width {
+                        topo::call_in_slot(&i, || ());
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+fn wide_with_children(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wide_with_children");
+    for width in &[100u32, 1_000, 10_000] {
+        group.throughput(Throughput::Elements(u64::from(*width)));
+        group.bench_with_input(BenchmarkId::from_parameter(width), width, |b, width| {
+            b.iter(|| {
+                topo::root(|| {
+                    for i in 0..*width {
+                        // like a table row with a few cells
+                        topo::call_in_slot(&i, || {
+                            topo::call(|| ());
+                            topo::call(|| ());
+                            topo::call(|| ());
+                        });
+                    }
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+fn call_recursively(depth: u32) {
+    if depth > 0 {
+        topo::call(|| call_recursively(depth - 1));
+    }
+}
+
+fn deep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep");
+    for depth in &[10u32, 100, 1_000] {
+        group.throughput(Throughput::Elements(u64::from(*depth)));
+        group.bench_with_input(BenchmarkId::from_parameter(depth), depth, |b, depth| {
+            b.iter(|| topo::root(|| call_recursively(*depth)))
+        });
+    }
+    group.finish();
+}
+
+criterion::criterion_group!(benches, wide_counted, wide_slotted, wide_with_children, deep);
+criterion::criterion_main!(benches);
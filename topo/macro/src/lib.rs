@@ -2,8 +2,12 @@
 
 use proc_macro::TokenStream;
 use syn::{
-    parse_macro_input, parse_quote, spanned::Spanned, AttributeArgs, Expr, ItemFn, Lit, Meta,
-    NestedMeta,
+    parse_macro_input, parse_quote,
+    spanned::Spanned,
+    visit_mut::{self, VisitMut},
+    AttributeArgs, Block, Expr, FnArg, GenericParam, ItemFn, Lifetime, Lit, Meta, NestedMeta,
+    ParenthesizedGenericArguments, ReturnType, Signature, Type, TypeBareFn, TypeImplTrait,
+    TypeParamBound, TypeReference,
 };
 
 #[proc_macro_attribute]
@@ -11,7 +15,20 @@ pub fn nested(args: TokenStream, input: TokenStream) -> TokenStream {
     let args: AttributeArgs = parse_macro_input!(args);
     let mut input_fn: ItemFn = syn::parse(input).unwrap();
 
-    let inner_block = input_fn.block;
+    let mut inner_block = input_fn.block;
+
+    // async fns are rewritten to return a future which enters the call's scope when polled
+    if input_fn.sig.asyncness.take().is_some() {
+        let output: Type = match &input_fn.sig.output {
+            ReturnType::Default => parse_quote!(()),
+            ReturnType::Type(_, ty) => (**ty).clone(),
+        };
+        let bound = bound_by_arguments(&mut input_fn.sig);
+        input_fn.sig.output = parse_quote!(-> impl std::future::Future<Output = #output> + #bound);
+        let scoped: Block = parse_quote! {{ topo::Scoped::new(async move #inner_block) }};
+        *inner_block = scoped;
+    }
+
     input_fn.block = if let Some(slot_expr) = slot_from_args(&args) {
         parse_quote! {{
             topo::call_in_slot(#slot_expr, move || #inner_block)
@@ -32,7 +49,7 @@ pub fn nested(args: TokenStream, input: TokenStream) -> TokenStream {
 fn slot_from_args(args: &[NestedMeta]) -> Option<Expr> {
     assert!(args.len() <= 1);
 
-    args.get(0).map(|arg| match arg {
+    args.first().map(|arg| match arg {
         NestedMeta::Meta(Meta::NameValue(kv)) => {
             assert!(
                 kv.path.is_ident("slot"),
@@ -47,3 +64,84 @@ fn slot_from_args(args: &[NestedMeta]) -> Option<Expr> {
         _ => panic!("only `slot = \"...\" argument is supported by #[nested]"),
     })
 }
+
+/// Names each elided lifetime in `sig`'s arguments and bounds every lifetime
+/// and type parameter by a new lifetime, which is returned. The future returned
+/// by an async fn captures all of its arguments, so it can only be bounded by a
+/// lifetime which they all outlive.
+fn bound_by_arguments(sig: &mut Signature) -> Lifetime {
+    let bound = Lifetime::new("'topo_nested", sig.fn_token.span());
+    let mut elided = ElidedLifetimes { bound: bound.clone(), named: Vec::new() };
+    for input in &mut sig.inputs {
+        match input {
+            FnArg::Receiver(receiver) => {
+                if let Some((_, lifetime @ None)) = &mut receiver.reference {
+                    *lifetime = Some(elided.next());
+                }
+            }
+            FnArg::Typed(arg) => elided.visit_type_mut(&mut arg.ty),
+        }
+    }
+
+    let generics = &mut sig.generics;
+    for param in &mut generics.params {
+        match param {
+            GenericParam::Lifetime(def) => def.bounds.push(bound.clone()),
+            GenericParam::Type(ty) => ty.bounds.push(TypeParamBound::Lifetime(bound.clone())),
+            GenericParam::Const(_) => (),
+        }
+    }
+    // lifetimes must be declared before other generic parameters
+    let mut params = vec![parse_quote!(#bound)];
+    params.extend(
+        elided.named.iter().map(|lifetime| -> GenericParam { parse_quote!(#lifetime: #bound) }),
+    );
+    params.extend(std::mem::take(&mut generics.params));
+    generics.params = params.into_iter().collect();
+    if generics.lt_token.is_none() {
+        generics.lt_token = Some(Default::default());
+        generics.gt_token = Some(Default::default());
+    }
+    bound
+}
+
+/// Names the elided lifetimes in argument types and bounds `impl Trait`
+/// arguments, ignoring those in function pointer and closure signatures.
+struct ElidedLifetimes {
+    bound: Lifetime,
+    named: Vec<Lifetime>,
+}
+
+impl ElidedLifetimes {
+    fn next(&mut self) -> Lifetime {
+        let name = format!("'topo_elided{}", self.named.len());
+        let lifetime = Lifetime::new(&name, self.bound.span());
+        self.named.push(lifetime.clone());
+        lifetime
+    }
+}
+
+impl VisitMut for ElidedLifetimes {
+    fn visit_type_reference_mut(&mut self, ty: &mut TypeReference) {
+        if ty.lifetime.is_none() {
+            ty.lifetime = Some(self.next());
+        }
+        visit_mut::visit_type_reference_mut(self, ty);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = self.next();
+        }
+    }
+
+    fn visit_type_impl_trait_mut(&mut self, ty: &mut TypeImplTrait) {
+        visit_mut::visit_type_impl_trait_mut(self, ty);
+        ty.bounds.push(TypeParamBound::Lifetime(self.bound.clone()));
+    }
+
+    fn visit_type_bare_fn_mut(&mut self, _: &mut TypeBareFn) {}
+
+    fn visit_parenthesized_generic_arguments_mut(&mut self, _: &mut ParenthesizedGenericArguments) {
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{CallId, CallPath, Callsite, PathSegment, Scope, Slot};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    hash::{BuildHasherDefault, Hasher},
+    panic::Location,
+    rc::Rc,
+};
+
+/// Calls the provided function as the root of a new call tree like
+/// [`crate::root`], keeping the [`Ancestry`] of each call made within it.
+///
+/// Every [`crate::call`], [`crate::call_in_slot`], and [`crate::nested`]
+/// function called from `op` is added to the returned `Ancestry`, including
+/// those made under nested calls to [`crate::root`]. While `op` runs, the
+/// `CallId`s it makes can also be resolved with [`CallId::parent`],
+/// [`CallId::path`] and the like. Calls made by [`crate::Scoped`] futures after
+/// they're returned from `op` aren't kept.
+///
+/// Ancestry has no cost for calls made outside of `ancestry`, and the calls
+/// kept are dropped with the returned `Ancestry`.
+///
+/// # Example
+///
+/// ```
+/// use topo::{ancestry, call, CallId};
+///
+/// let ((outer, inner), calls) = ancestry(|| {
+///     let (outer, inner) = call(|| (CallId::current(), call(CallId::current)));
+///     assert_eq!(inner.parent(), Some(outer), "resolved while the calls are kept");
+///     (outer, inner)
+/// });
+///
+/// assert_eq!(inner.parent(), None, "no longer kept");
+/// assert_eq!(calls.parent(inner), Some(outer));
+/// assert!(calls.is_descendant_of(inner, outer));
+/// assert_eq!(calls.location(outer).unwrap().line(), line!() - 8);
+/// ```
+pub fn ancestry<F, R>(op: F) -> (R, Ancestry)
+where
+    F: FnOnce() -> R,
+{
+    let ancestry = Ancestry::default();
+    let scope = Scope { ancestry: Some(ancestry.clone()), ..Scope::default() };
+    let ret = scope.make_child(Callsite::root(), Slot::Count(0), |_| None).enter(op);
+    (ret, ancestry)
+}
+
+/// The calls made within [`ancestry`], which resolve the [`CallId`]s made there
+/// to their parents, source locations, and [`CallPath`]s.
+///
+/// Clones share the same calls, which are dropped along with the last clone.
+#[derive(Clone, Default)]
+pub struct Ancestry {
+    calls: Rc<RefCell<Calls>>,
+}
+
+/// How a [`CallId`] was made.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Call {
+    pub callsite: Callsite,
+    /// `None` for the root of a call tree
+    pub parent: Option<CallId>,
+    pub slot: Slot,
+}
+
+/// The calls kept by an [`Ancestry`], by their `CallId`s.
+type Calls = HashMap<CallId, Call, BuildHasherDefault<IdHasher>>;
+
+impl Ancestry {
+    /// Returns the ancestry kept by the enclosing call to [`ancestry`], if any.
+    pub(crate) fn current() -> Option<Self> {
+        Scope::with_current(|current| current.ancestry.clone())
+    }
+
+    /// Records that `id` was made by `call`.
+    pub(crate) fn insert(&self, id: CallId, call: Call) {
+        self.calls.borrow_mut().insert(id, call);
+    }
+
+    fn call(&self, id: CallId) -> Option<Call> {
+        self.calls.borrow().get(&id).copied()
+    }
+
+    /// Returns the `CallId` which was current when `id` was created, or `None`
+    /// if it's the root of its call tree or wasn't made here. See
+    /// [`CallId::parent`].
+    pub fn parent(&self, id: CallId) -> Option<CallId> {
+        self.call(id).and_then(|call| call.parent)
+    }
+
+    /// Returns an iterator over the parent of `id`, the parent's parent, and so
+    /// on up to the root of its call tree. See [`CallId::ancestors`].
+    pub fn ancestors(&self, id: CallId) -> impl Iterator<Item = CallId> {
+        let ancestry = self.clone();
+        std::iter::successors(self.parent(id), move |&parent| ancestry.parent(parent))
+    }
+
+    /// Returns true if `ancestor` is one of the [`Ancestry::ancestors`] of `id`.
+    /// See [`CallId::is_descendant_of`].
+    pub fn is_descendant_of(&self, id: CallId, ancestor: CallId) -> bool {
+        self.ancestors(id).any(|parent| parent == ancestor)
+    }
+
+    /// Returns the source location of the call which created `id`, if it was
+    /// made here without a slot. See [`CallId::location`].
+    pub fn location(&self, id: CallId) -> Option<&'static Location<'static>> {
+        match self.call(id)? {
+            Call { slot: Slot::Count(_), callsite, .. } if callsite != Callsite::root() => {
+                Some(callsite.location)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the [`CallPath`] of `id`, which is empty if it wasn't made here.
+    /// See [`CallId::path`].
+    pub fn path(&self, id: CallId) -> CallPath {
+        let mut segments = Vec::new();
+        let calls = std::iter::successors(self.call(id), |call| self.call(call.parent?));
+        for call in calls {
+            match call.slot {
+                Slot::Count(count) if call.callsite != Callsite::root() => {
+                    let location = call.callsite.location;
+                    segments.push(PathSegment::Call {
+                        file: location.file().to_owned(),
+                        line: location.line(),
+                        column: location.column(),
+                        count,
+                    })
+                }
+                Slot::Value { hash, .. } => segments.push(PathSegment::Slot { hash }),
+                // roots are implementation details of this crate
+                Slot::Count(_) => (),
+            }
+        }
+        segments.reverse();
+        CallPath::new(segments)
+    }
+}
+
+impl Debug for Ancestry {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Ancestry").field("calls", &self.calls.borrow().len()).finish()
+    }
+}
+
+/// Hashes [`CallId`]s, which are already hashes and only need to be folded into
+/// 64 bits.
+#[derive(Default)]
+struct IdHasher(u64);
+
+impl Hasher for IdHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(*byte);
+        }
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.0 = (n as u64) ^ ((n >> 64) as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{call, call_in_slot, root_in};
+
+    #[test]
+    fn nothing_kept_outside() {
+        let ((), calls) = ancestry(|| ());
+        assert_eq!(calls.calls.borrow().len(), 1, "only the root");
+        assert!(call(|| Ancestry::current().is_none()));
+    }
+
+    #[test]
+    fn nested_roots_are_kept() {
+        let ((outer, rooted), calls) = ancestry(|| {
+            let outer = call(CallId::current);
+            let rooted = root_in("nested", || call(CallId::current));
+            (outer, rooted)
+        });
+
+        assert_eq!(calls.ancestors(outer).count(), 1, "the root");
+        assert_eq!(calls.ancestors(rooted).count(), 1, "the nested root");
+        assert!(!calls.is_descendant_of(rooted, calls.parent(outer).unwrap()));
+    }
+
+    #[test]
+    fn dropped_with_the_ancestry() {
+        let ((), calls) = ancestry(|| {
+            for i in 0..1000u32 {
+                call_in_slot(&i, || call(|| ()));
+            }
+        });
+        assert_eq!(calls.calls.borrow().len(), 2001, "each call and the root");
+
+        let weak = Rc::downgrade(&calls.calls);
+        drop(calls);
+        assert!(weak.upgrade().is_none());
+    }
+}
@@ -72,7 +72,10 @@
 //! assert_ne!(bob, alice_hello);
 //! ```
 //!
-//! Internally, slots are interned in a global [`dyn-cache`].
+//! Slots are hashed into the [`CallId`]s made with them along with their type,
+//! so no memory is retained for slot values which are no longer used. Slots
+//! are told apart only by their hashes, so in rare cases two distinct slots may
+//! collide and produce the same [`CallId`].
 //!
 //! [Incremental Computing]: https://en.wikipedia.org/wiki/Incremental_computing
 //! [caching problem]: https://en.wikipedia.org/wiki/Cache_(computing)
@@ -129,14 +132,57 @@
 ///
 /// See [`call_in_slot`] and [`CallId`]'s documentation for more information on
 /// how slots are used.
+///
+/// # Async functions
+///
+/// `async fn`s can be nested too. They enter their child scope when they're
+/// called, and the returned future is a [`Scoped`] future which re-enters it
+/// every time it's polled:
+///
+/// ```
+/// use futures::executor::block_on;
+/// use topo::{call, CallId};
+///
+/// #[topo::nested]
+/// async fn fetch(name: String) -> (CallId, String) {
+///     futures::future::ready(()).await;
+///     (call(CallId::current), name)
+/// }
+///
+/// let (first, second) = call(|| (fetch("a".into()), fetch("b".into())));
+/// let (first, second) = (block_on(first), block_on(second));
+/// assert_ne!(first.0, second.0, "each call has its own scope");
+/// ```
+///
+/// Like the future of any other `async fn`, the returned future captures the
+/// function's arguments, including any references they hold.
 #[doc(inline)]
 pub use topo_macro::nested;
 
-use slot::{OpaqueSlot, Slot};
-use std::{borrow::Borrow, cell::RefCell, hash::Hash, panic::Location};
+use ancestry::Call;
+use record::Recorder;
+use slot::Slot;
+use std::{
+    borrow::Borrow,
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    hash::{BuildHasherDefault, Hash, Hasher},
+    panic::Location,
+    rc::Rc,
+};
 
+mod ancestry;
+mod path;
+mod record;
+mod scoped;
 mod slot;
 
+pub use ancestry::{ancestry, Ancestry};
+pub use path::{CallPath, PathSegment};
+pub use record::{record, CallNode, CallTree, SlotValue};
+pub use scoped::{Scoped, WithEnv};
+
 /// Calls the provided function as a child of [`CallId::current`], using for a
 /// slot the number of times the given source location has been called during
 /// the current parent's scope.
@@ -183,12 +229,12 @@ pub fn call<F, R>(op: F) -> R
 where
     F: FnOnce() -> R,
 {
-    #[derive(Clone, Debug, Eq, Hash, PartialEq)]
-    struct CallCount(u32);
-
     let callsite = Callsite::here();
-    let count = CallCount(callsite.current_count());
-    Scope::with_current(|p| p.make_child(callsite, &count)).enter(op)
+    Scope::with_current(|p| {
+        let count = Slot::Count(p.increment_count(callsite));
+        p.make_child(callsite, count, |id| Some(CallNode::new(id, callsite.location, None)))
+    })
+    .enter(op)
 }
 
 /// Calls the provided function as a child of [`CallId::current`], using `slot`
@@ -262,10 +308,58 @@ where
 pub fn call_in_slot<F, Q, R, S>(slot: &Q, op: F) -> R
 where
     F: FnOnce() -> R,
-    Q: Eq + Hash + ToOwned<Owned = S> + ?Sized,
-    S: Borrow<Q> + Eq + Hash + Send + 'static,
+    Q: Hash + ToOwned<Owned = S> + ?Sized,
+    S: Borrow<Q> + Hash + Send + 'static,
+{
+    enter_slot(Location::caller(), slot, SlotValue::new::<Q>, op)
+}
+
+/// Calls the provided function as a child of [`CallId::current`] like
+/// [`call_in_slot`], additionally recording the [`Debug`] representation of
+/// `slot` when called within [`record`].
+///
+/// # Example
+///
+/// ```
+/// use topo::{call_in_debug_slot, record};
+///
+/// let ((), tree) = record(|| call_in_debug_slot("bob", || ()));
+///
+/// let slot = tree.calls()[0].slot().unwrap();
+/// assert_eq!(slot.type_name(), "str");
+/// assert_eq!(slot.value(), Some("\"bob\""));
+/// ```
+#[track_caller]
+pub fn call_in_debug_slot<F, Q, R, S>(slot: &Q, op: F) -> R
+where
+    F: FnOnce() -> R,
+    Q: Debug + Hash + ToOwned<Owned = S> + ?Sized,
+    S: Borrow<Q> + Hash + Send + 'static,
 {
-    Scope::with_current(|p| p.make_child(Callsite::here(), slot)).enter(op)
+    enter_slot(Location::caller(), slot, || SlotValue::with_debug(slot), op)
+}
+
+/// Enters a child of the current scope with `slot`, recording the call at
+/// `location` if the current scope has a recorder. Slotted calls all share
+/// this function's callsite, so their `CallId`s don't depend on where they're
+/// made.
+fn enter_slot<F, Q, R, S>(
+    location: &'static Location<'static>,
+    slot: &Q,
+    describe: impl FnOnce() -> SlotValue,
+    op: F,
+) -> R
+where
+    F: FnOnce() -> R,
+    Q: Hash + ToOwned<Owned = S> + ?Sized,
+    S: Borrow<Q> + Hash + Send + 'static,
+{
+    Scope::with_current(|p| {
+        p.make_child(Callsite::here(), Slot::make(slot), |id| {
+            Some(CallNode::new(id, location, Some(describe())))
+        })
+    })
+    .enter(op)
 }
 
 /// Calls the provided function as the root of a new call tree, ignoring the
@@ -283,12 +377,56 @@ where
 /// let dependent = || topo::call(topo::CallId::current);
 /// assert_ne!(topo::call(dependent), topo::call(dependent));
 /// ```
+///
+/// Every call to `root` produces the same root `CallId`, so the same functions
+/// called within two roots will produce the same `CallId`s. See [`root_in`] for
+/// running independent instances of a call tree.
 pub fn root<F, R>(op: F) -> R
 where
     F: FnOnce() -> R,
 {
-    illicit::hide::<Scope>();
-    call(op)
+    let scope = root_scope(Slot::Count(0));
+    Scope::hide_current();
+    scope.enter(op)
+}
+
+/// Calls the provided function as the root of a new call tree in `namespace`,
+/// ignoring the current `CallId`.
+///
+/// Roots in different namespaces have different `CallId`s, as do all of the
+/// calls made within them. This allows running several independent instances
+/// of the same call tree, for example to give each one its own state in a
+/// shared cache.
+///
+/// # Example
+///
+/// ```
+/// use topo::{call, root, root_in, CallId};
+///
+/// let widget = || call(CallId::current);
+///
+/// let first_tab = root_in("first tab", widget);
+/// assert_eq!(first_tab, root_in("first tab", widget), "namespaces are deterministic");
+///
+/// assert_ne!(first_tab, root_in("second tab", widget));
+/// assert_ne!(first_tab, root(widget));
+/// ```
+pub fn root_in<F, Q, R, S>(namespace: &Q, op: F) -> R
+where
+    F: FnOnce() -> R,
+    Q: Hash + ToOwned<Owned = S> + ?Sized,
+    S: Borrow<Q> + Hash + Send + 'static,
+{
+    root_scope(Slot::make(namespace)).enter(op)
+}
+
+/// Returns the scope of a new root with `slot`, which records its calls and
+/// keeps their ancestry if the current scope does.
+fn root_scope(slot: Slot) -> Rc<Scope> {
+    let (recorder, ancestry) =
+        Scope::with_current(|current| (current.recorder.clone(), current.ancestry.clone()));
+    let scope = Scope { recorder, ancestry, ..Scope::default() };
+    scope.make_child(Callsite::root(), slot, |_| None)
 }
 
 /// Identifies the scope of a nested function call in a way that can be
@@ -342,23 +480,29 @@ where
 ///
 /// See [`call_in_slot`] and [`nested`] for examples.
 ///
+/// Slot values aren't kept. Each is hashed along with its type into the
+/// 128-bit hash of the `CallId`, and `CallId`s are compared by their hashes,
+/// so slots only need to implement [`Hash`]. Two distinct slots can collide
+/// and produce the same `CallId`, although for 128-bit hashes this is
+/// vanishingly rare.
+///
 /// # Roots
 ///
 /// The topmost parent or "root" of a callgraph can be defined in two ways:
 ///
 /// 1. a [`call`] or [`call_in_slot`] invocation with no parent implicitly
-/// creates its own root
-/// 2. an explicit call to [`root`] creates a new subgraph regardless of the
-/// current parent
+///    creates its own root
+/// 2. an explicit call to [`root`] or [`root_in`] creates a new subgraph
+///    regardless of the current parent
 ///
-/// See [`root`] for examples.
+/// See [`root`] and [`root_in`] for examples.
 ///
 /// # `CallId` and multiple threads
 ///
-/// The [`illicit`] environment used for tracking the current `CallId` is
-/// thread-local, but values used to track slots are
-/// interned in a global cache. This means that two different threads calling
-/// an identical chain of nested functions can observe identical `CallId`s:
+/// The current `CallId` is tracked thread-locally, but `CallId`s are derived
+/// only from callsites and slots. This means that two
+/// different threads calling an identical chain of nested functions can observe
+/// identical `CallId`s:
 ///
 /// ```
 /// # use topo::{call, root, CallId};
@@ -398,21 +542,34 @@ where
 /// [`call`]: `crate::call`
 /// [`call_in_slot`]: `crate::call_in_slot`
 /// [`root`]: `crate::root`
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+/// [`root_in`]: `crate::root_in`
+///
+/// # Ancestry
+///
+/// `CallId`s are 128-bit hashes of their callsite, slot, and parent, so
+/// they're cheap to copy and compare but don't refer to their parents. Calls
+/// made within [`ancestry`] are kept so that their `CallId`s can be resolved to
+/// their [`parent`], [`location`], and [`path`]. Outside of it, these return
+/// `None` or an empty path.
+///
+/// [`ancestry`]: crate::ancestry()
+/// [`parent`]: CallId::parent
+/// [`location`]: CallId::location
+/// [`path`]: CallId::path
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub struct CallId {
-    callsite: Callsite,
-    parent: Slot<CallId>,
-    slot: OpaqueSlot,
+    hash: u128,
 }
 
 impl CallId {
-    /// Returns the root `CallId`.
+    /// Returns the placeholder `CallId` which is current outside of any call.
     pub(crate) fn root() -> Self {
-        Self {
-            callsite: Callsite::here(),
-            parent: Slot::fake(),
-            slot: Slot::<String>::fake().into(),
-        }
+        Self { hash: 0 }
+    }
+
+    /// Returns a new child of this `CallId`.
+    fn child(self, callsite: Callsite, slot: Slot) -> Self {
+        Self { hash: path::wide_hash(&(self.hash, callsite, slot)) }
     }
 
     /// Returns the current `CallId`.
@@ -420,19 +577,138 @@ impl CallId {
         Scope::with_current(|current| current.id)
     }
 
-    pub(crate) fn child<Q, S>(&self, callsite: Callsite, slot: &Q) -> Self
-    where
-        Q: Eq + Hash + ToOwned<Owned = S> + ?Sized,
-        S: Borrow<Q> + Eq + Hash + Send + 'static,
-    {
-        Self { callsite, parent: Slot::make(self), slot: Slot::make(slot).into() }
+    /// Returns a representation of this `CallId` which is stable across
+    /// processes and builds, made from the source location and count or slot of
+    /// each call between this `CallId` and its root. See [`CallPath`].
+    ///
+    /// Empty outside of [`ancestry`], which keeps the calls this is made from.
+    /// See [`Ancestry::path`] to resolve it afterwards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use topo::{ancestry, call, CallId};
+    ///
+    /// ancestry(|| {
+    ///     let id = call(CallId::current);
+    ///
+    ///     // the string representation names the source location of each call
+    ///     assert_eq!(id.path().to_string(), format!("{}:{}:14[0]", file!(), line!() - 3));
+    /// });
+    /// ```
+    ///
+    /// [`ancestry`]: crate::ancestry()
+    pub fn path(&self) -> CallPath {
+        Ancestry::current().map(|ancestry| ancestry.path(*self)).unwrap_or_default()
+    }
+
+    /// Returns the `CallId` which was current when this one was created, or
+    /// `None` if this is the root of its call tree or if it's called outside of
+    /// the [`ancestry`] in which this was made.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use topo::{ancestry, call, CallId};
+    ///
+    /// ancestry(|| {
+    ///     let root_id = CallId::current();
+    ///     let (parent, child) = call(|| (CallId::current(), call(CallId::current)));
+    ///
+    ///     assert_eq!(child.parent(), Some(parent));
+    ///     assert_eq!(parent.parent(), Some(root_id));
+    ///     assert_eq!(root_id.parent(), None);
+    /// });
+    /// ```
+    ///
+    /// [`ancestry`]: crate::ancestry()
+    pub fn parent(&self) -> Option<CallId> {
+        Ancestry::current()?.parent(*self)
+    }
+
+    /// Returns an iterator over this `CallId`'s parent, the parent's parent, and
+    /// so on up to the root of the call tree. Empty outside of [`ancestry`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use topo::{ancestry, call, CallId};
+    ///
+    /// ancestry(|| {
+    ///     let (outer, inner) = call(|| (CallId::current(), call(CallId::current)));
+    ///     let ancestors = inner.ancestors().collect::<Vec<_>>();
+    ///     assert_eq!(ancestors.len(), 2, "the outer call and the root");
+    ///     assert_eq!(ancestors[0], outer);
+    /// });
+    /// ```
+    ///
+    /// [`ancestry`]: crate::ancestry()
+    pub fn ancestors(&self) -> impl Iterator<Item = CallId> {
+        let id = *self;
+        Ancestry::current().into_iter().flat_map(move |ancestry| ancestry.ancestors(id))
+    }
+
+    /// Returns true if `other` is one of this `CallId`'s [`ancestors`].
+    ///
+    /// A `CallId` is not its own descendant.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use topo::{ancestry, call, CallId};
+    ///
+    /// ancestry(|| {
+    ///     let outer = CallId::current();
+    ///     let inner = call(|| call(CallId::current));
+    ///     let sibling = call(CallId::current);
+    ///
+    ///     assert!(inner.is_descendant_of(&outer));
+    ///     assert!(!inner.is_descendant_of(&sibling));
+    ///     assert!(!outer.is_descendant_of(&outer));
+    /// });
+    /// ```
+    ///
+    /// [`ancestors`]: CallId::ancestors
+    pub fn is_descendant_of(&self, other: &CallId) -> bool {
+        self.ancestors().any(|ancestor| ancestor == *other)
+    }
+
+    /// Returns the source location of the call which created this `CallId`.
+    ///
+    /// Returns `None` for roots, for calls made with a slot, which are
+    /// identified by their slot regardless of where they're called, and outside
+    /// of [`ancestry`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use topo::{ancestry, call, call_in_slot, CallId};
+    ///
+    /// ancestry(|| {
+    ///     let id = call(CallId::current);
+    ///     assert_eq!(id.location().unwrap().line(), line!() - 1);
+    ///
+    ///     assert_eq!(call_in_slot("slot", CallId::current).location(), None);
+    /// });
+    /// ```
+    ///
+    /// [`ancestry`]: crate::ancestry()
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        Ancestry::current()?.location(*self)
+    }
+}
+
+// see CallId::path for a representation which names the calls made
+impl Debug for CallId {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_tuple("CallId").field(&format_args!("#{:032x}", self.hash)).finish()
     }
 }
 
 /// A value unique to the source location where it is created.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug)]
 struct Callsite {
-    location: usize,
+    location: &'static Location<'static>,
 }
 
 impl Callsite {
@@ -443,91 +719,165 @@ impl Callsite {
         Location::caller().into()
     }
 
-    /// Returns the number of times this callsite has been seen in the current
-    /// call.
-    pub fn current_count(self) -> u32 {
-        Scope::with_current(|current| {
-            if let Some(c) = current.callsite_counts.borrow().iter().find(|(site, _)| site == &self)
-            {
-                c.1
-            } else {
-                0
-            }
-        })
+    /// Returns the callsite of the outermost call made by [`root`].
+    fn root() -> Self {
+        Self::here()
     }
 }
 
 impl From<&'static Location<'static>> for Callsite {
     fn from(location: &'static Location<'static>) -> Self {
-        Self {
-            // the pointer value for a given location is enough to differentiate it from all others
-            location: location as *const _ as usize,
+        Self { location }
+    }
+}
+
+// the pointer value for a given location is enough to differentiate it from all others
+impl PartialEq for Callsite {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.location, other.location)
+    }
+}
+
+impl Eq for Callsite {}
+
+impl Hash for Callsite {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        (self.location as *const Location).hash(hasher)
+    }
+}
+
+thread_local! {
+    /// The innermost scope entered on this thread, if any.
+    static CURRENT_SCOPE: RefCell<Option<Rc<Scope>>> = RefCell::default();
+}
+
+/// The number of times each callsite has been called in a scope.
+type CallsiteCounts = HashMap<Callsite, u32, BuildHasherDefault<CallsiteHasher>>;
+
+/// Hashes [`Callsite`]s, which are written to the hasher as a single pointer
+/// and don't need a general-purpose hash function.
+#[derive(Default)]
+struct CallsiteHasher(u64);
+
+impl Hasher for CallsiteHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(*byte);
         }
     }
+
+    fn write_usize(&mut self, n: usize) {
+        // multiplying by a large odd constant spreads the aligned low bits of pointers
+        self.0 = (n as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
 }
 
 /// The root of a sub-graph within the overall topology.
 ///
 /// The current `Scope` contains the local [`CallId`] and a count of how often
-/// each of its children has been called.
+/// each of its children has been called. Scopes are kept in a thread-local
+/// stack rather than the `illicit` environment, so entering one is cheap
+/// regardless of what else the environment holds.
 #[derive(Debug)]
 struct Scope {
     /// current id
     id: CallId,
     /// # times each callsite's type has been observed during this scope.
-    callsite_counts: RefCell<Vec<(Callsite, u32)>>,
+    callsite_counts: RefCell<CallsiteCounts>,
+    /// collects calls made in this scope when within [`record`]
+    recorder: Option<Recorder>,
+    /// keeps how calls made in this scope were made when within [`ancestry()`]
+    ancestry: Option<Ancestry>,
 }
 
 impl Scope {
-    /// Mark a child Point in the topology, returning an illicit layer which will reference the new
-    /// point when entered.
-    #[inline(never)] // this is only called by functions with more generic args than this one
-    fn make_child<Q, S>(&self, callsite: Callsite, slot: &Q) -> illicit::Layer
-    where
-        Q: Eq + Hash + ToOwned<Owned = S> + ?Sized,
-        S: Borrow<Q> + Eq + Hash + Send + 'static,
-    {
-        self.increment_count(callsite);
+    /// Mark a child Point in the topology, returning a scope which will be current when entered.
+    /// `describe` is called to record the child if this scope has a recorder.
+    fn make_child(
+        &self,
+        callsite: Callsite,
+        slot: Slot,
+        describe: impl FnOnce(CallId) -> Option<CallNode>,
+    ) -> Rc<Self> {
+        let id = self.id.child(callsite, slot);
+        if let Some(ancestry) = &self.ancestry {
+            // a call without a parent is the root of its own tree
+            let parent = if self.id == CallId::root() { None } else { Some(self.id) };
+            ancestry.insert(id, Call { callsite, parent, slot });
+        }
+        let recorder = self.recorder.as_ref().map(|recorder| match describe(id) {
+            Some(node) => recorder.child(node),
+            None => recorder.clone(),
+        });
         let child_point = Self {
             callsite_counts: RefCell::new(Default::default()),
-            id: self.id.child(callsite, slot),
+            id,
+            recorder,
+            ancestry: self.ancestry.clone(),
         };
-        illicit::Layer::new().offer(child_point)
+        Rc::new(child_point)
     }
 
-    /// Runs the provided closure with access to the current [`Point`].
+    /// Calls `op` with this as the current scope, restoring the previous scope
+    /// when it returns or unwinds.
+    fn enter<R>(self: Rc<Self>, op: impl FnOnce() -> R) -> R {
+        struct Restore(Option<Rc<Scope>>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT_SCOPE.with(|current| *current.borrow_mut() = self.0.take());
+            }
+        }
+
+        let _restore = Restore(CURRENT_SCOPE.with(|current| current.replace(Some(self))));
+        op()
+    }
+
+    /// Removes the current scope for the remainder of the scope which was
+    /// entered before it, as [`illicit::hide`] does for environment values.
+    fn hide_current() {
+        CURRENT_SCOPE.with(|current| current.borrow_mut().take());
+    }
+
+    /// Runs the provided closure with access to the current [`Scope`].
     fn with_current<F, Out>(op: F) -> Out
     where
         F: FnOnce(&Scope) -> Out,
     {
-        if let Ok(current) = illicit::get::<Scope>() {
-            op(&*current)
-        } else {
-            op(&Scope::default())
+        match CURRENT_SCOPE.with(|current| current.borrow().clone()) {
+            Some(current) => op(&current),
+            None => op(&Scope::default()),
         }
     }
 
-    fn increment_count(&self, callsite: Callsite) {
+    /// Returns the number of times `callsite` has been called in this scope
+    /// before this call.
+    fn increment_count(&self, callsite: Callsite) -> u32 {
         let mut counts = self.callsite_counts.borrow_mut();
-
-        if let Some((_, count)) = counts.iter_mut().find(|(site, _)| site == &callsite) {
-            *count += 1;
-        } else {
-            counts.push((callsite, 1));
-        }
+        let count = counts.entry(callsite).or_insert(0);
+        *count += 1;
+        *count - 1
     }
 }
 
 impl Default for Scope {
     fn default() -> Self {
-        Self { id: CallId::root(), callsite_counts: Default::default() }
+        Self {
+            id: CallId::root(),
+            callsite_counts: Default::default(),
+            recorder: None,
+            ancestry: None,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{call, root};
+    use crate::{ancestry, call, root, root_in};
     use std::{collections::HashSet, sync::mpsc::channel, thread};
 
     #[test]
@@ -605,6 +955,35 @@ mod tests {
         assert_eq!(call(independent), call(independent));
     }
 
+    #[test]
+    fn root_hides_current_scope() {
+        let (outer, after) = call(|| {
+            let outer = CallId::current();
+            root(|| ());
+            (outer, CallId::current())
+        });
+        assert_ne!(outer, after, "the caller's scope is hidden after entering a root");
+        assert_eq!(after, CallId::current());
+    }
+
+    #[test]
+    fn namespaced_roots_are_disjoint() {
+        let tree = || call(|| (CallId::current(), call_in_slot("child", CallId::current)));
+
+        let (first, second) = (root_in(&1u32, tree), root_in(&2u32, tree));
+        assert_ne!(first.0, second.0);
+        assert_ne!(first.1, second.1);
+        assert_eq!(first, root_in(&1u32, tree));
+
+        let (kept, calls) = ancestry(|| root_in(&1u32, tree));
+        assert_eq!(kept, first, "keeping ancestry doesn't change CallIds");
+        assert_eq!(calls.ancestors(kept.1).count(), 2, "namespaced roots have no parent");
+
+        let path_in = |namespace: u32| ancestry(|| root_in(&namespace, || tree().0.path())).0;
+        assert_ne!(path_in(1), path_in(2), "namespaces are included in paths");
+        assert_eq!(path_in(1), path_in(1));
+    }
+
     #[test]
     fn loop_over_map_with_keys_in_slots() {
         let slots = vec!["first", "second", "third", "fourth", "fifth"];
@@ -631,8 +1010,8 @@ mod tests {
     #[test]
     fn threads_and_ids() {
         let returns_two_ids = || {
-            let first = call(|| CallId::current());
-            let second = call(|| CallId::current());
+            let first = call(CallId::current);
+            let second = call(CallId::current);
             assert_ne!(first, second, "these are always distinct calls");
             (first, second)
         };
@@ -648,4 +1027,77 @@ mod tests {
         let (first, second) = (recv_ids.recv().unwrap(), recv_ids.recv().unwrap());
         assert_eq!(first, second);
     }
+
+    #[test]
+    fn paths_name_each_call() {
+        let get_path = || {
+            ancestry(|| {
+                call(|| {
+                    call(|| ());
+                    call_in_slot(&"slotted", || call(CallId::current)).path()
+                })
+            })
+            .0
+        };
+        let path = get_path();
+        assert_eq!(path, get_path(), "each root has the same path");
+
+        let segments = path.segments();
+        assert_eq!(segments.len(), 3);
+        assert!(
+            matches!(&segments[0], PathSegment::Call { file, count: 0, .. } if file == file!())
+        );
+        assert_eq!(segments[1], PathSegment::Slot { hash: path::stable_hash("slotted") });
+        assert!(matches!(segments[2], PathSegment::Call { count: 0, .. }));
+
+        let (siblings, calls) = ancestry(|| (call(CallId::current), call(CallId::current)));
+        assert_ne!(calls.path(siblings.0), calls.path(siblings.1));
+        assert!(siblings.0.path().segments().is_empty(), "no longer kept");
+    }
+
+    #[test]
+    fn ancestry_of_calls() {
+        assert_eq!(CallId::current().parent(), None);
+        assert_eq!(CallId::current().ancestors().count(), 0);
+
+        let ((root_id, outer, slotted, inner), calls) = ancestry(|| {
+            let root_id = CallId::current();
+            let (outer, slotted, inner) = call(|| {
+                let (slotted, inner) =
+                    call_in_slot(&1u8, || (CallId::current(), call(CallId::current)));
+                assert_eq!(inner.parent(), Some(slotted));
+                (CallId::current(), slotted, inner)
+            });
+            (root_id, outer, slotted, inner)
+        });
+        assert_eq!(calls.parent(root_id), None);
+        assert_eq!(calls.ancestors(inner).collect::<Vec<_>>(), [slotted, outer, root_id]);
+        assert!(calls.is_descendant_of(inner, outer));
+        assert!(!calls.is_descendant_of(outer, inner));
+
+        assert_eq!(calls.location(outer).unwrap().file(), file!());
+        assert_eq!(calls.location(slotted), None);
+        assert_eq!(inner.parent(), None, "not kept outside of ancestry");
+    }
+
+    #[test]
+    fn ancestry_is_not_shared() {
+        let tree = |slot: u32| call_in_slot(&slot, || call(CallId::current));
+        let (first, first_calls) = ancestry(|| tree(1));
+        let (second, second_calls) = ancestry(|| tree(2));
+
+        assert_eq!(first_calls.ancestors(first).count(), 2);
+        assert_eq!(second_calls.parent(first), None, "only calls made within are kept");
+        assert_eq!(second_calls.ancestors(second).count(), 2);
+        assert_eq!(first, ancestry(|| tree(1)).0, "CallIds don't depend on ancestry");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn paths_round_trip() {
+        let path = ancestry(|| call_in_slot(&10u64, || call(CallId::current)).path()).0;
+        let serialized = serde_json::to_string(&path).unwrap();
+        let deserialized: CallPath = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(path, deserialized);
+    }
 }
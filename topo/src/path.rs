@@ -0,0 +1,172 @@
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    hash::{Hash, Hasher},
+};
+
+/// A representation of a [`crate::CallId`] which is stable across processes
+/// and builds, returned by [`crate::CallId::path`] and [`crate::Ancestry::path`].
+///
+/// A `CallPath` is the chain of calls from the root of the `CallId`'s call
+/// tree to the `CallId` itself. Unlike a `CallId`, it doesn't depend on pointer
+/// addresses, so it can be persisted, sent to another process, or printed for
+/// debugging.
+///
+/// Two `CallPath`s are equal when their `CallId`s were made by the same chain
+/// of calls. Changing the source code, for example by moving a call to a
+/// different line, changes the paths of all calls nested within it. Source
+/// files are named as they are by [`std::panic::Location::file`], which is
+/// relative to the workspace root for local crates.
+///
+/// Serializable with the `serde` feature.
+///
+/// # Example
+///
+/// ```
+/// use topo::{ancestry, call, call_in_slot, CallId, PathSegment};
+///
+/// let get_path = || ancestry(|| call_in_slot("first", || call(CallId::current).path()));
+/// let path = get_path().0;
+/// assert_eq!(path, get_path().0);
+///
+/// match path.segments() {
+///     [PathSegment::Slot { .. }, PathSegment::Call { line, count, .. }] => {
+///         assert_eq!(*line, line!() - 6);
+///         assert_eq!(*count, 0);
+///     }
+///     other => panic!("unexpected path {:?}", other),
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct CallPath {
+    segments: Vec<PathSegment>,
+}
+
+impl CallPath {
+    pub(crate) fn new(segments: Vec<PathSegment>) -> Self {
+        Self { segments }
+    }
+
+    /// Returns the calls in this path, starting with the outermost.
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+}
+
+impl Display for CallPath {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" > ")?;
+            }
+            write!(f, "{}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single call within a [`CallPath`].
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum PathSegment {
+    /// A call made with [`crate::call`] or a `#[nested]` function without a
+    /// slot.
+    Call {
+        /// The source file in which the call was made.
+        file: String,
+        /// The line on which the call was made.
+        line: u32,
+        /// The column at which the call was made.
+        column: u32,
+        /// The number of previous calls at the same location in the same
+        /// parent.
+        count: u32,
+    },
+    /// A call made with [`crate::call_in_slot`] or a `#[nested]` function with a
+    /// slot. Like their `CallId`s, these don't depend on the location of the
+    /// call.
+    Slot {
+        /// The hash of the slot, computed with a fixed algorithm. It doesn't
+        /// include the slot's type, so slots of different types with the same
+        /// representation have the same segment. It's stable as long as the
+        /// slot's [`std::hash::Hash`] implementation is, which isn't the case
+        /// for slots containing pointers, `TypeId`s, or `CallId`s.
+        hash: u64,
+    },
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            PathSegment::Call { file, line, column, count } => {
+                write!(f, "{}:{}:{}[{}]", file, line, column, count)
+            }
+            PathSegment::Slot { hash } => write!(f, "[#{:016x}]", hash),
+        }
+    }
+}
+
+/// Returns a hash of `value` which doesn't vary between processes.
+pub(crate) fn stable_hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = Fnv1a::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns a 128-bit hash of `value`, wide enough for [`crate::CallId`]s to be
+/// compared by their hashes alone. Unlike [`stable_hash`], it's used with
+/// values which contain pointers.
+pub(crate) fn wide_hash<T: Hash + ?Sized>(value: &T) -> u128 {
+    let mut hasher = Fnv1a128::default();
+    value.hash(&mut hasher);
+    hasher.0
+}
+
+/// The 64-bit variant of the [FNV-1a] hash, chosen for its simplicity over its
+/// quality.
+///
+/// [FNV-1a]: https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// The 128-bit variant of the [FNV-1a] hash.
+///
+/// [FNV-1a]: https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function
+struct Fnv1a128(u128);
+
+impl Default for Fnv1a128 {
+    fn default() -> Self {
+        Self(0x6c62_272e_07bb_0142_62b8_2175_6295_c58d)
+    }
+}
+
+impl Hasher for Fnv1a128 {
+    fn finish(&self) -> u64 {
+        self.0 as u64
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u128::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0000_0100_0000_0000_0000_0000_013b);
+        }
+    }
+}
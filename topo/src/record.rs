@@ -0,0 +1,333 @@
+use crate::{CallId, Callsite, Scope, Slot};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Display, Formatter, Result as FmtResult, Write},
+    mem::take,
+    panic::Location,
+    rc::Rc,
+};
+
+/// Calls the provided function as the root of a new call tree like [`crate::root`],
+/// recording each call made within it.
+///
+/// Every [`crate::call`], [`crate::call_in_slot`],
+/// [`crate::call_in_debug_slot`], and [`crate::nested`] function called from
+/// `op` is added to the returned [`CallTree`], including
+/// those made under nested calls to [`crate::root`]. Calls made by
+/// [`crate::Scoped`] futures after they're returned from `op` aren't recorded.
+///
+/// Recording has no cost for calls made outside of `record`.
+///
+/// # Example
+///
+/// ```
+/// use topo::{call, call_in_debug_slot, record};
+///
+/// let ((), tree) = record(|| {
+///     call(|| {
+///         for name in &["alice", "bob"] {
+///             call_in_debug_slot(name, || ());
+///         }
+///     })
+/// });
+///
+/// let outer = &tree.calls()[0];
+/// assert_eq!(outer.location().line(), line!() - 8);
+/// assert_eq!(outer.children().len(), 2);
+///
+/// let bob = outer.children()[1].slot().unwrap();
+/// assert_eq!(bob.type_name(), "&str");
+/// assert_eq!(bob.value(), Some("\"bob\""));
+///
+/// println!("{}", tree);
+/// println!("{}", tree.to_dot());
+/// ```
+pub fn record<F, R>(op: F) -> (R, CallTree)
+where
+    F: FnOnce() -> R,
+{
+    let recorder = Recorder::default();
+    let scope = Scope { recorder: Some(recorder.clone()), ..Scope::default() };
+    let ret = scope.make_child(Callsite::root(), Slot::Count(0), |_| None).enter(op);
+    (ret, recorder.finish())
+}
+
+/// The calls made within [`record`], in the order they were made.
+///
+/// Formatting a `CallTree` with [`Display`] produces an indented outline with
+/// one call per line, naming the source location of each call and either its
+/// count or its slot. Slots are named by their type, followed by their value if
+/// they were passed to [`crate::call_in_debug_slot`]:
+///
+/// ```text
+/// src/main.rs:12:5[0]
+///   src/main.rs:14:13[&str = "alice"]
+///   src/main.rs:14:13[&str = "bob"]
+/// ```
+///
+/// Calls in the same parent with the same slot at the same source location
+/// share a [`CallId`], which is easy to spot in this outline.
+#[derive(Clone, Debug, Default)]
+pub struct CallTree {
+    calls: Vec<CallNode>,
+}
+
+impl CallTree {
+    /// Returns the outermost calls in the tree.
+    pub fn calls(&self) -> &[CallNode] {
+        &self.calls
+    }
+
+    /// Returns a [Graphviz] `digraph` of the tree, with one node per call and
+    /// edges from parents to their children.
+    ///
+    /// [Graphviz]: https://graphviz.org/doc/info/lang.html
+    pub fn to_dot(&self) -> String {
+        fn add_node(out: &mut String, next_index: &mut usize, node: &CallNode) -> usize {
+            let index = *next_index;
+            *next_index += 1;
+
+            let label = node.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(out, "    n{} [label=\"{}\"];", index, label).unwrap();
+            for child in &node.children {
+                let child_index = add_node(out, next_index, child);
+                writeln!(out, "    n{} -> n{};", index, child_index).unwrap();
+            }
+            index
+        }
+
+        let mut out = String::from("digraph calls {\n    node [shape=box];\n");
+        let mut next_index = 0;
+        for call in &self.calls {
+            add_node(&mut out, &mut next_index, call);
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+impl Display for CallTree {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        fn write_node(f: &mut Formatter, depth: usize, node: &CallNode) -> FmtResult {
+            writeln!(f, "{:indent$}{}", "", node, indent = depth * 2)?;
+            for child in &node.children {
+                write_node(f, depth + 1, child)?;
+            }
+            Ok(())
+        }
+
+        for call in &self.calls {
+            write_node(f, 0, call)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single call within a [`CallTree`].
+#[derive(Clone, Debug)]
+pub struct CallNode {
+    id: CallId,
+    location: &'static Location<'static>,
+    slot: Option<SlotValue>,
+    count: u32,
+    children: Vec<CallNode>,
+}
+
+impl CallNode {
+    pub(crate) fn new(
+        id: CallId,
+        location: &'static Location<'static>,
+        slot: Option<SlotValue>,
+    ) -> Self {
+        Self { id, location, slot, count: 0, children: Vec::new() }
+    }
+
+    /// Returns the `CallId` which was current during the call.
+    pub fn id(&self) -> CallId {
+        self.id
+    }
+
+    /// Returns the source location of the call.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// Returns the slot passed to [`crate::call_in_slot`] or
+    /// [`crate::call_in_debug_slot`], if any.
+    pub fn slot(&self) -> Option<&SlotValue> {
+        self.slot.as_ref()
+    }
+
+    /// Returns the number of calls made at the same location in the same parent
+    /// before this one.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Returns the calls made within this one.
+    pub fn children(&self) -> &[CallNode] {
+        &self.children
+    }
+}
+
+impl Display for CallNode {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let location = self.location;
+        write!(f, "{}:{}:{}", location.file(), location.line(), location.column())?;
+        match &self.slot {
+            Some(SlotValue { type_name, value: Some(value) }) => {
+                write!(f, "[{} = {}]", type_name, value)
+            }
+            Some(SlotValue { type_name, value: None }) => write!(f, "[{}]", type_name),
+            None => write!(f, "[{}]", self.count),
+        }
+    }
+}
+
+/// The slot of a recorded call.
+#[derive(Clone, Debug)]
+pub struct SlotValue {
+    type_name: &'static str,
+    value: Option<String>,
+}
+
+impl SlotValue {
+    pub(crate) fn new<Q: ?Sized>() -> Self {
+        Self { type_name: std::any::type_name::<Q>(), value: None }
+    }
+
+    pub(crate) fn with_debug<Q: Debug + ?Sized>(value: &Q) -> Self {
+        Self { value: Some(format!("{:?}", value)), ..Self::new::<Q>() }
+    }
+
+    /// Returns the name of the slot's type.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns the slot's [`Debug`] representation, if it was passed to
+    /// [`crate::call_in_debug_slot`].
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+}
+
+/// Collects the calls made within a [`record`] invocation, inherited by each
+/// [`Scope`] from its parent.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Recorder {
+    calls: Rc<RefCell<Calls>>,
+    parent: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct Calls {
+    /// each recorded call and the index of its parent, in the order they were made
+    nodes: Vec<(CallNode, Option<usize>)>,
+    /// # of calls made at each location in each parent
+    counts: HashMap<(Option<usize>, *const Location<'static>), u32>,
+}
+
+impl Recorder {
+    /// Records `node` as a child of this recorder's call, returning a recorder
+    /// for calls made within it.
+    pub(crate) fn child(&self, mut node: CallNode) -> Self {
+        let mut calls = self.calls.borrow_mut();
+        let count = calls.counts.entry((self.parent, node.location as *const _)).or_insert(0);
+        node.count = *count;
+        *count += 1;
+
+        calls.nodes.push((node, self.parent));
+        Self { calls: self.calls.clone(), parent: Some(calls.nodes.len() - 1) }
+    }
+
+    fn finish(self) -> CallTree {
+        let mut nodes = take(&mut self.calls.borrow_mut().nodes);
+
+        // children are always recorded after their parents, so walking backwards
+        // completes each node before it's moved into its parent
+        let mut calls = Vec::new();
+        while let Some((mut node, parent)) = nodes.pop() {
+            node.children.reverse();
+            match parent {
+                Some(parent) => nodes[parent].0.children.push(node),
+                None => calls.push(node),
+            }
+        }
+        calls.reverse();
+        CallTree { calls }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{call, call_in_debug_slot, call_in_slot, root};
+
+    #[test]
+    fn records_nested_calls() {
+        let (ids, tree) = record(|| {
+            let outer = call(|| {
+                for i in 0..3u8 {
+                    call(|| call_in_debug_slot(&(i % 2), || ()));
+                }
+                CallId::current()
+            });
+            let rooted = root(|| call(CallId::current));
+            (outer, rooted)
+        });
+
+        let calls = tree.calls();
+        assert_eq!(calls.len(), 2, "calls under a nested root are recorded in place");
+        assert_eq!(calls[0].id(), ids.0);
+        assert_eq!(calls[1].id(), ids.1);
+
+        let loop_calls = calls[0].children();
+        assert_eq!(loop_calls.iter().map(CallNode::count).collect::<Vec<_>>(), [0, 1, 2]);
+
+        let slots = loop_calls
+            .iter()
+            .map(|c| c.children()[0].slot().unwrap().value().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(slots, ["0", "1", "0"]);
+    }
+
+    #[test]
+    fn exports_text_and_dot() {
+        let ((), tree) = record(|| {
+            call(|| {
+                call_in_debug_slot("a \"quoted\" slot", || ());
+            })
+        });
+        let outer = format!("{}:{}:13[0]", file!(), line!() - 4);
+        let inner = format!("{}:{}:17[str = \"a \\\"quoted\\\" slot\"]", file!(), line!() - 4);
+
+        assert_eq!(tree.to_string(), format!("{}\n  {}\n", outer, inner));
+
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph calls {"));
+        assert!(dot.contains(&format!("n0 [label=\"{}\"];", outer)));
+        assert!(dot.contains(r#"[str = \"a \\\"quoted\\\" slot\"]"#));
+        assert!(dot.contains("n0 -> n1;"));
+    }
+
+    #[test]
+    fn slots_without_debug() {
+        #[derive(Clone, Eq, Hash, PartialEq)]
+        struct Opaque;
+
+        let ((), tree) = record(|| call_in_slot(&Opaque, || ()));
+        let slot = tree.calls()[0].slot().unwrap();
+        assert!(slot.type_name().ends_with("Opaque"));
+        assert_eq!(slot.value(), None);
+        assert!(tree.to_string().ends_with("Opaque]\n"));
+    }
+
+    #[test]
+    fn nothing_recorded_outside() {
+        let ((), tree) = record(|| ());
+        assert!(tree.calls().is_empty());
+        assert!(call(|| Scope::with_current(|current| current.recorder.is_none())));
+    }
+}
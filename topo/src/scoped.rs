@@ -0,0 +1,131 @@
+use crate::{CallId, CallsiteCounts, Scope};
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    future::Future,
+    mem::take,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+/// A future which is polled within the scope of the [`CallId`] which was
+/// current when it was created.
+///
+/// Futures are usually polled by an executor which knows nothing of the
+/// [`CallId`] that created them, so calls made within them would otherwise be
+/// roots of their own. A `Scoped` future re-enters its captured scope on every
+/// poll, so the calls it makes are children of that `CallId` no matter how
+/// many times the future has been polled before. Calls counted by [`crate::call`]
+/// continue counting across polls.
+///
+/// A `Scoped` future is [`Send`] when the future it wraps is, so it can be
+/// spawned on a multi-threaded executor. See [`Scoped::with_env`] to also
+/// capture the [`illicit`] environment.
+///
+/// `Scoped` is returned by `async fn`s annotated with [`crate::nested`], which
+/// enter their child scope when called rather than when first polled.
+///
+/// # Example
+///
+/// ```
+/// use futures::executor::block_on;
+/// use topo::{call, CallId, Scoped};
+///
+/// let (parent, future) = call(|| {
+///     let parent = CallId::current();
+///     (parent, Scoped::new(async { (CallId::current(), call(CallId::current)) }))
+/// });
+///
+/// // polled outside of any call, but with its parent's CallId
+/// let (current, child) = block_on(future);
+/// assert_eq!(current, parent);
+/// assert_ne!(child, parent);
+/// ```
+pub struct Scoped<F> {
+    inner: Pin<Box<F>>,
+    id: CallId,
+    callsite_counts: CallsiteCounts,
+}
+
+impl<F: Future> Scoped<F> {
+    /// Wraps `inner` so that it's polled within the scope of
+    /// [`CallId::current`].
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            id: CallId::current(),
+            callsite_counts: CallsiteCounts::default(),
+        }
+    }
+
+    /// Wraps `inner` so that it's polled within the scope of
+    /// [`CallId::current`] and a snapshot of the current [`illicit`]
+    /// environment. Unlike other `Scoped` futures, the returned future isn't
+    /// `Send`, because environment snapshots aren't.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use futures::executor::block_on;
+    /// use topo::Scoped;
+    ///
+    /// let future = illicit::Layer::new()
+    ///     .offer(String::from("hello"))
+    ///     .enter(|| Scoped::with_env(async { illicit::expect::<String>().clone() }));
+    ///
+    /// assert!(illicit::get::<String>().is_err());
+    /// assert_eq!(block_on(future), "hello");
+    /// ```
+    pub fn with_env(inner: F) -> Scoped<WithEnv<F>> {
+        Scoped::new(WithEnv { inner: Box::pin(inner), env: illicit::Snapshot::get() })
+    }
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let scope = Rc::new(Scope {
+            id: this.id,
+            callsite_counts: take(&mut this.callsite_counts).into(),
+            recorder: None,
+            ancestry: None,
+        });
+        let inner = &mut this.inner;
+        let poll = scope.clone().enter(|| inner.as_mut().poll(cx));
+
+        // hold on to the scope's state for the next poll
+        this.callsite_counts = scope.callsite_counts.take();
+        poll
+    }
+}
+
+impl<F> Debug for Scoped<F> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("Scoped").field("id", &self.id).finish()
+    }
+}
+
+/// A future which is polled within a snapshot of the [`illicit`] environment,
+/// returned by [`Scoped::with_env`].
+pub struct WithEnv<F> {
+    inner: Pin<Box<F>>,
+    env: illicit::Snapshot,
+}
+
+impl<F: Future> Future for WithEnv<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let inner = &mut this.inner;
+        illicit::Layer::from(this.env.clone()).enter(|| inner.as_mut().poll(cx))
+    }
+}
+
+impl<F> Debug for WithEnv<F> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("WithEnv").field("env", &self.env).finish()
+    }
+}
@@ -1,149 +1,83 @@
-use dyn_cache::sync::SendCache;
-use once_cell::sync::Lazy;
-use parking_lot::Mutex;
+use crate::path::{stable_hash, wide_hash};
 use std::{
-    any::{type_name, TypeId},
+    any::TypeId,
     borrow::Borrow,
-    collections::HashMap,
     fmt::{Debug, Formatter, Result as FmtResult},
-    hash::{Hash, Hasher},
-    marker::PhantomData,
+    hash::Hash,
 };
 
-static TOKENS: Lazy<Mutex<SendCache>> = Lazy::new(|| Mutex::new(SendCache::default()));
-
-/// A unique identifer in the global cache. Each type can have
-/// [`std::u32::MAX`] unique values cached. Constructed with [`Token::make`],
-/// which will always produce the same value for the same input.
-///
-/// # Memory Usage
-///
-/// Token inputs are not yet dropped and care should be taken when creating
-/// large numbers of them, as the memory used over time will grow with
-/// proportion to the number of unique tokens created.
+/// Differentiates between calls at the same callsite within the same parent.
 ///
-/// See [issue #141](https://github.com/anp/moxie/issues/141) for future work.
-///
-/// A typed token can be converted into an [`OpaqueToken`] to allow
-/// differentiating between unique values of different types.
-pub(crate) struct Slot<T> {
-    index: u32,
-    ty: PhantomData<T>,
+/// Slot values are hashed into the [`crate::CallId`]s made with them rather than
+/// interned, so there is no global state to reclaim when a slot's value is no
+/// longer used. A slot's type is included in its hash, so slots of different
+/// types are distinct even when their values hash the same.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub(crate) enum Slot {
+    /// The number of times the callsite had been called in the parent.
+    Count(u32),
+    /// A value passed to [`crate::call_in_slot`].
+    Value {
+        /// the stable hash of the value, used in [`crate::CallPath`]s
+        hash: u64,
+        /// the hash of the value and its type, used in `CallId`s
+        typed: u128,
+    },
 }
 
-impl<T> Slot<T>
-where
-    T: Eq + Hash + Send + 'static,
-{
-    /// Makes a unique token from the provided value, interning it in the global
-    /// cache. Later calls with the same input will return the same token.
-    pub fn make<Q>(value: &Q) -> Slot<T>
+impl Slot {
+    /// Makes a slot from the provided value. Later calls with an equal input
+    /// will return an equal slot.
+    pub fn make<Q, S>(value: &Q) -> Self
     where
-        Q: Eq + Hash + ToOwned<Owned = T> + ?Sized,
-        T: Borrow<Q>,
+        Q: Hash + ToOwned<Owned = S> + ?Sized,
+        S: Borrow<Q> + Hash + Send + 'static,
     {
-        static INDICES: Lazy<Mutex<HashMap<TypeId, u32>>> =
-            Lazy::new(|| Mutex::new(HashMap::new()));
-        let mut existing_tokens = TOKENS.lock();
-
-        match existing_tokens.get(value, &()) {
-            Ok(token) => *token,
-            Err(miss) => {
-                let (to_store, new_token) = miss.init(|_| {
-                    let mut indices = INDICES.lock();
-                    let count = indices.entry(TypeId::of::<T>()).or_default();
-                    *count += 1;
-                    let new_token = Self { index: *count, ty: PhantomData };
-                    (new_token, new_token)
-                });
-                existing_tokens.store(to_store);
-                new_token
-            }
-        }
-    }
-
-    /// Fabricate a token. Used for e.g. creating a root `crate::CallId`.
-    pub(crate) fn fake() -> Self {
-        Self { index: 0, ty: PhantomData }
-    }
-}
-
-impl<T> Clone for Slot<T> {
-    fn clone(&self) -> Self {
-        Self { index: self.index, ty: PhantomData }
+        Slot::Value { hash: stable_hash(value), typed: wide_hash(&(TypeId::of::<S>(), value)) }
     }
 }
 
-impl<T> Copy for Slot<T> {}
-
-impl<T> Debug for Slot<T> {
+impl Debug for Slot {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        f.debug_struct("Token").field("index", &self.index).field("ty", &type_name::<T>()).finish()
-    }
-}
-
-impl<Q, T> From<&Q> for Slot<T>
-where
-    Q: Eq + Hash + ToOwned<Owned = T> + ?Sized,
-    T: Borrow<Q> + Eq + Hash + Send + 'static,
-{
-    fn from(query: &Q) -> Self {
-        Slot::make(query)
-    }
-}
-
-impl<T> Hash for Slot<T> {
-    fn hash<H: Hasher>(&self, hasher: &mut H) {
-        self.index.hash(hasher)
-    }
-}
-
-impl<T> PartialEq for Slot<T> {
-    fn eq(&self, other: &Self) -> bool {
-        self.index == other.index
-    }
-}
-impl<T> Eq for Slot<T> {}
-
-impl<T> PartialOrd for Slot<T> {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.index.partial_cmp(&other.index)
-    }
-}
-impl<T> Ord for Slot<T> {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.index.cmp(&other.index)
-    }
-}
-
-/// A unique type-erased identifier for a cached value.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
-pub(crate) struct OpaqueSlot {
-    ty: TypeId,
-    index: u32,
-}
-
-impl<T: 'static> From<Slot<T>> for OpaqueSlot {
-    fn from(token: Slot<T>) -> Self {
-        OpaqueSlot { index: token.index, ty: TypeId::of::<T>() }
+        match self {
+            Slot::Count(count) => f.debug_tuple("Count").field(count).finish(),
+            Slot::Value { hash, .. } => write!(f, "Value(#{:016x})", hash),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{call_in_slot, CallId};
+    use std::sync::Arc;
 
     #[test]
-    fn make_tokens() {
-        let foo: Slot<String> = Slot::make("foo");
+    fn make_slots() {
+        let foo = Slot::make("foo");
         assert_eq!(foo, Slot::make("foo"));
         assert_ne!(foo, Slot::make("bar"));
     }
 
     #[test]
-    fn make_opaque() {
-        let first: OpaqueSlot = Slot::make(&10u8).into();
-        let second: OpaqueSlot = Slot::make(&10u16).into();
-        assert_ne!(first, second);
+    fn slots_differ_by_type() {
+        assert_ne!(Slot::make(&10u8), Slot::make(&10u16));
+        assert_ne!(Slot::make(&10u32), Slot::make(&10i32));
+    }
+
+    #[test]
+    fn slots_need_only_hash() {
+        #[derive(Clone, Hash)]
+        struct HashOnly(u8);
+
+        assert_eq!(Slot::make(&HashOnly(1)), Slot::make(&HashOnly(1)));
+        assert_ne!(Slot::make(&HashOnly(1)), Slot::make(&HashOnly(2)));
+    }
+
+    #[test]
+    fn slots_are_not_retained() {
+        let value = Arc::new(1u8);
+        let _id = call_in_slot(&value, CallId::current);
+        assert_eq!(Arc::strong_count(&value), 1, "the CallId doesn't hold its slot");
     }
 }
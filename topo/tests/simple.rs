@@ -16,3 +16,81 @@ fn invoke_test_topo() {
         }
     });
 }
+
+#[test]
+fn nested_async_fns_keep_their_scope() {
+    use futures::{executor::block_on, future::poll_fn, task::Poll};
+
+    #[topo::nested]
+    async fn ids_across_await() -> (CallId, CallId, CallId) {
+        let before = topo::call(CallId::current);
+        let mut yielded = false;
+        poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await;
+        let after = topo::call(CallId::current);
+        (CallId::current(), before, after)
+    }
+
+    let run = || topo::root(|| (ids_across_await(), ids_across_await()));
+    let (first, second) = run();
+    let (first, second) = (block_on(first), block_on(second));
+    assert_ne!(first.0, second.0, "each call gets a unique CallId");
+    assert_ne!(first.1, first.2, "calls continue counting after an await");
+
+    let (first_again, _) = run();
+    assert_eq!(block_on(first_again), first, "CallIds are stable across executions");
+}
+
+#[test]
+fn nested_async_fns_borrow_their_arguments() {
+    use futures::executor::block_on;
+
+    #[topo::nested]
+    async fn greet(name: &str) -> usize {
+        futures::future::ready(()).await;
+        name.len()
+    }
+
+    struct Greeter {
+        greeting: String,
+    }
+
+    impl Greeter {
+        #[topo::nested]
+        async fn greet<'a>(&self, name: &'a str, _: impl AsRef<str>) -> (CallId, String) {
+            (CallId::current(), format!("{}, {}", self.greeting, name))
+        }
+    }
+
+    let name = String::from("alice");
+    assert_eq!(block_on(greet(&name)), 5);
+
+    let greeter = Greeter { greeting: String::from("hello") };
+    let (first, second) = topo::call(|| (greeter.greet(&name, ""), greeter.greet(&name, "")));
+    let (first, second) = (block_on(first), block_on(second));
+    assert_eq!(first.1, "hello, alice");
+    assert_ne!(first.0, second.0, "each call gets a unique CallId");
+}
+
+#[test]
+fn nested_async_fns_are_send() {
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[topo::nested]
+    async fn shared(name: &str) -> usize {
+        futures::future::ready(()).await;
+        name.len()
+    }
+
+    let future = shared("alice");
+    assert_send(&future);
+    assert_send(&topo::Scoped::new(async { CallId::current() }));
+}
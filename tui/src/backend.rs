@@ -0,0 +1,104 @@
+//! Backends present a drawn [`Grid`] to a terminal.
+
+use crate::grid::Grid;
+
+/// Presents each revision's [`Grid`] to the user.
+pub trait Backend {
+    /// Called after every revision with the newly drawn grid.
+    fn present(&mut self, grid: &Grid);
+}
+
+impl<F> Backend for F
+where
+    F: FnMut(&Grid),
+{
+    fn present(&mut self, grid: &Grid) {
+        self(grid)
+    }
+}
+
+#[cfg(feature = "crossterm")]
+pub use self::crossterm::CrosstermBackend;
+
+#[cfg(feature = "crossterm")]
+mod crossterm {
+    use super::Backend;
+    use crate::{
+        event::{KeyCode, KeyEvent},
+        grid::Grid,
+    };
+    use crossterm::{
+        cursor::MoveTo,
+        event::{KeyCode as CtCode, KeyEvent as CtEvent, KeyModifiers},
+        queue,
+        style::Print,
+        terminal::{Clear, ClearType},
+    };
+    use std::io::Write;
+
+    /// Draws grids to a terminal with [crossterm], overwriting the screen from
+    /// its top left corner.
+    ///
+    /// Requires the `crossterm` feature.
+    ///
+    /// [crossterm]: https://docs.rs/crossterm
+    pub struct CrosstermBackend<W: Write> {
+        out: W,
+    }
+
+    impl<W: Write> CrosstermBackend<W> {
+        /// Creates a backend which writes to `out`, usually `std::io::stdout()`.
+        pub fn new(out: W) -> Self {
+            Self { out }
+        }
+
+        fn write_grid(&mut self, grid: &Grid) -> crossterm::Result<()> {
+            for (y, row) in (0..).zip(grid.rows()) {
+                let line: String = row.iter().collect();
+                queue!(
+                    self.out,
+                    MoveTo(0, y),
+                    Print(line.trim_end()),
+                    Clear(ClearType::UntilNewLine)
+                )?;
+            }
+            self.out.flush()?;
+            Ok(())
+        }
+    }
+
+    impl<W: Write> Backend for CrosstermBackend<W> {
+        fn present(&mut self, grid: &Grid) {
+            if let Err(e) = self.write_grid(grid) {
+                tracing::warn!(error = %e, "failed to present grid to terminal");
+            }
+        }
+    }
+
+    impl KeyEvent {
+        /// Converts a crossterm key event, returning `None` for keys which
+        /// moxie-tui doesn't support.
+        ///
+        /// Requires the `crossterm` feature.
+        pub fn from_crossterm(event: CtEvent) -> Option<Self> {
+            let code = match event.code {
+                CtCode::Char(c) => KeyCode::Char(c),
+                CtCode::Enter => KeyCode::Enter,
+                CtCode::Tab => KeyCode::Tab,
+                CtCode::BackTab => KeyCode::BackTab,
+                CtCode::Backspace => KeyCode::Backspace,
+                CtCode::Esc => KeyCode::Esc,
+                CtCode::Up => KeyCode::Up,
+                CtCode::Down => KeyCode::Down,
+                CtCode::Left => KeyCode::Left,
+                CtCode::Right => KeyCode::Right,
+                _ => return None,
+            };
+            Some(KeyEvent {
+                code,
+                ctrl: event.modifiers.contains(KeyModifiers::CONTROL),
+                alt: event.modifiers.contains(KeyModifiers::ALT),
+            })
+        }
+    }
+}
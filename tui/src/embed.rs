@@ -0,0 +1,170 @@
+//! Embedding APIs which run a moxie root function and draw its output.
+
+use crate::{
+    backend::Backend,
+    event::{KeyCode, KeyEvent},
+    grid::Grid,
+    node::{Handler, Node},
+};
+use futures::task::LocalSpawn;
+use moxie::{runtime::RunLoop, state, Key};
+use std::{
+    cell::{Ref, RefCell},
+    rc::Rc,
+    task::Waker,
+};
+use topo::CallId;
+
+/// Wrapper around `moxie::runtime::RunLoop` and a root function which returns a
+/// [`Node`]. After each revision the returned node is drawn into the loop's
+/// [`Grid`] and presented by its [`Backend`], if any.
+#[must_use]
+pub struct TuiLoop {
+    inner: RunLoop<Box<dyn FnMut()>>,
+    screen: Rc<RefCell<Screen>>,
+}
+
+struct Screen {
+    grid: Grid,
+    backend: Option<Box<dyn Backend>>,
+    focusable: Vec<(CallId, Handler)>,
+    focused: Option<CallId>,
+    set_focus: Option<Key<Option<CallId>>>,
+}
+
+impl TuiLoop {
+    /// Construct a new `TuiLoop` which draws to a grid of the given size.
+    ///
+    /// On its own a `TuiLoop` is inert and must either have its `run_once`
+    /// method called when a re-render is needed, or be driven by
+    /// [`TuiLoop::run_on_state_changes`].
+    pub fn new(width: u16, height: u16, mut root: impl FnMut() -> Node + 'static) -> Self {
+        let screen = Rc::new(RefCell::new(Screen {
+            grid: Grid::new(width, height),
+            backend: None,
+            focusable: Vec::new(),
+            focused: None,
+            set_focus: None,
+        }));
+
+        let drawn = screen.clone();
+        let inner = RunLoop::new(Box::new(move || {
+            let (focused, set_focus) = state(|| None::<CallId>);
+            #[allow(clippy::redundant_closure)] // removing the closure syntax makes this FnOnce
+            let node = topo::call(|| root());
+
+            let mut focusable = Vec::new();
+            node.focusable(&mut focusable);
+            // fall back to the first focusable node if the focused one went away
+            let focused = focused
+                .filter(|id| focusable.iter().any(|(f, _)| f == id))
+                .or_else(|| focusable.first().map(|(id, _)| *id));
+
+            let screen = &mut *drawn.borrow_mut();
+            let area = screen.grid.area();
+            screen.grid.clear();
+            node.draw(&mut screen.grid, area, focused.as_ref());
+            screen.focusable = focusable;
+            screen.focused = focused;
+            screen.set_focus = Some(set_focus);
+            if let Some(backend) = &mut screen.backend {
+                backend.present(&screen.grid);
+            }
+        }) as Box<dyn FnMut()>);
+
+        Self { inner, screen }
+    }
+
+    /// Sets the backend which presents the grid after each revision.
+    pub fn set_backend(&mut self, backend: impl Backend + 'static) {
+        self.screen.borrow_mut().backend = Some(Box::new(backend));
+    }
+
+    /// Sets the executor that will be used to spawn normal priority tasks.
+    pub fn set_task_executor(&mut self, sp: impl LocalSpawn + 'static) {
+        self.inner.set_task_executor(sp);
+    }
+
+    /// Sets the [`std::task::Waker`] which will be called when state variables
+    /// change.
+    pub fn set_state_change_waker(&mut self, wk: Waker) {
+        self.inner.set_state_change_waker(wk);
+    }
+
+    /// Changes the size of the grid, taking effect in the next revision.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.screen.borrow_mut().grid = Grid::new(width, height);
+    }
+
+    /// Run the root function in a fresh `moxie::Revision` and draw the returned
+    /// node.
+    pub fn run_once(&mut self) {
+        self.inner.run_once();
+    }
+
+    /// Returns the grid drawn by the most recent revision.
+    pub fn grid(&self) -> Ref<'_, Grid> {
+        Ref::map(self.screen.borrow(), |s| &s.grid)
+    }
+
+    /// Returns a handle for delivering keyboard events to the loop's nodes.
+    pub fn input(&self) -> Input {
+        Input { screen: self.screen.clone() }
+    }
+
+    /// Runs a revision and draws its output every time a state variable
+    /// changes. See [`moxie::runtime::RunLoop::run_on_state_changes`].
+    pub async fn run_on_state_changes(self) {
+        self.inner.run_on_state_changes().await;
+    }
+}
+
+/// Delivers keyboard events to the focused node of a [`TuiLoop`].
+///
+/// `Tab` and `BackTab` move focus between the nodes which have handlers
+/// registered with [`Node::on_key`], in the order they were drawn. All other
+/// keys are passed to the focused node's handler. Focus defaults to the first
+/// focusable node.
+#[derive(Clone)]
+pub struct Input {
+    screen: Rc<RefCell<Screen>>,
+}
+
+impl Input {
+    /// Delivers `key` to the focused node, returning true if it was handled.
+    /// Focus changes apply to the following keys immediately and are drawn in
+    /// the next revision.
+    pub fn send_key(&self, key: impl Into<KeyEvent>) -> bool {
+        let key = key.into();
+        let mut screen = self.screen.borrow_mut();
+        let len = screen.focusable.len();
+        let current =
+            match screen.focusable.iter().position(|(id, _)| screen.focused.as_ref() == Some(id)) {
+                Some(current) => current,
+                None => return false,
+            };
+
+        let next = match key.code {
+            KeyCode::Tab => (current + 1) % len,
+            KeyCode::BackTab => (current + len - 1) % len,
+            _ => {
+                let handler = screen.focusable[current].1.clone();
+                // handlers are free to use this input handle
+                drop(screen);
+                handler(&key);
+                return true;
+            }
+        };
+        let next = screen.focusable[next].0;
+        screen.focused = Some(next);
+        if let Some(set_focus) = &screen.set_focus {
+            set_focus.set(Some(next));
+        }
+        true
+    }
+
+    /// Returns the id of the node which will receive keys, if any.
+    pub fn focused(&self) -> Option<CallId> {
+        self.screen.borrow().focused
+    }
+}
@@ -0,0 +1,49 @@
+//! Keyboard events delivered to focused nodes.
+
+/// A key which was pressed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum KeyCode {
+    /// A printable character.
+    Char(char),
+    /// The enter or return key.
+    Enter,
+    /// The tab key, which moves focus to the next focusable node.
+    Tab,
+    /// Shift+tab, which moves focus to the previous focusable node.
+    BackTab,
+    /// The backspace key.
+    Backspace,
+    /// The escape key.
+    Esc,
+    /// The up arrow key.
+    Up,
+    /// The down arrow key.
+    Down,
+    /// The left arrow key.
+    Left,
+    /// The right arrow key.
+    Right,
+}
+
+/// A key press along with the modifiers held while it was pressed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct KeyEvent {
+    /// The key which was pressed.
+    pub code: KeyCode,
+    /// Whether the control key was held.
+    pub ctrl: bool,
+    /// Whether the alt key was held.
+    pub alt: bool,
+}
+
+impl From<KeyCode> for KeyEvent {
+    fn from(code: KeyCode) -> Self {
+        Self { code, ctrl: false, alt: false }
+    }
+}
+
+impl From<char> for KeyEvent {
+    fn from(c: char) -> Self {
+        KeyCode::Char(c).into()
+    }
+}
@@ -0,0 +1,131 @@
+//! An in-memory grid of characters which interfaces are drawn to.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A rectangular region of a [`Grid`], measured in cells.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Rect {
+    /// The column of the region's left edge.
+    pub x: u16,
+    /// The row of the region's top edge.
+    pub y: u16,
+    /// The number of columns in the region.
+    pub width: u16,
+    /// The number of rows in the region.
+    pub height: u16,
+}
+
+impl Rect {
+    /// Returns the region inside of a one-cell border around this one.
+    pub fn inner(self) -> Self {
+        Self {
+            x: self.x.saturating_add(1),
+            y: self.y.saturating_add(1),
+            width: self.width.saturating_sub(2),
+            height: self.height.saturating_sub(2),
+        }
+    }
+
+    /// Returns true if the region contains no cells.
+    pub fn is_empty(self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// A fixed-size grid of characters, indexed by column and row from the top
+/// left corner.
+///
+/// Interfaces are drawn to a grid on every revision, which is then presented
+/// by a [`crate::backend::Backend`] or inspected directly in tests.
+///
+/// # Example
+///
+/// ```
+/// use moxie_tui::grid::Grid;
+///
+/// let mut grid = Grid::new(8, 2);
+/// grid.put_str(1, 1, 4, "hello");
+/// assert_eq!(grid.get(1, 1), Some('h'));
+/// assert_eq!(grid.to_string(), "\n hell");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Grid {
+    width: u16,
+    height: u16,
+    cells: Vec<char>,
+}
+
+impl Grid {
+    /// Creates a grid of the given size filled with spaces.
+    pub fn new(width: u16, height: u16) -> Self {
+        Self { width, height, cells: vec![' '; width as usize * height as usize] }
+    }
+
+    /// Returns the number of columns in the grid.
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Returns the number of rows in the grid.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Returns the region covering the whole grid.
+    pub fn area(&self) -> Rect {
+        Rect { x: 0, y: 0, width: self.width, height: self.height }
+    }
+
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the character at the given cell, if it's inside the grid.
+    pub fn get(&self, x: u16, y: u16) -> Option<char> {
+        self.index(x, y).map(|i| self.cells[i])
+    }
+
+    /// Sets the character at the given cell, ignoring cells outside the grid.
+    pub fn set(&mut self, x: u16, y: u16, c: char) {
+        if let Some(i) = self.index(x, y) {
+            self.cells[i] = c;
+        }
+    }
+
+    /// Writes `s` to the row `y` starting at column `x`, truncating it to at
+    /// most `max_width` cells.
+    pub fn put_str(&mut self, x: u16, y: u16, max_width: u16, s: &str) {
+        for (offset, c) in (0..max_width).zip(s.chars()) {
+            self.set(x.saturating_add(offset), y, c);
+        }
+    }
+
+    /// Fills every cell with a space.
+    pub fn clear(&mut self) {
+        self.cells.iter_mut().for_each(|c| *c = ' ');
+    }
+
+    /// Returns the contents of each row with trailing spaces removed.
+    pub fn lines(&self) -> Vec<String> {
+        self.rows().map(|row| row.iter().collect::<String>().trim_end().to_owned()).collect()
+    }
+
+    /// Returns the cells of each row.
+    pub fn rows(&self) -> impl Iterator<Item = &[char]> {
+        self.cells.chunks(self.width.max(1) as usize).take(self.height as usize)
+    }
+}
+
+impl Display for Grid {
+    /// Writes each row with trailing spaces removed, omitting trailing empty
+    /// rows.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let lines = self.lines();
+        let len = lines.iter().rposition(|l| !l.is_empty()).map_or(0, |last| last + 1);
+        f.write_str(&lines[..len].join("\n"))
+    }
+}
@@ -0,0 +1,182 @@
+//! Tools for declaratively constructing and incrementally updating terminal
+//! interfaces. Based on the [`moxie`] UI runtime.
+//!
+//! Interfaces are trees of [`node::Node`]s returned from a root function on
+//! every [`moxie::runtime::Revision`]. A [`embed::TuiLoop`] draws each tree
+//! into an in-memory [`grid::Grid`] of characters which can be presented to a
+//! terminal by a [`backend::Backend`] or inspected directly in tests.
+//!
+//! Nodes become focusable with [`node::Node::on_key`]. Keyboard events are
+//! delivered to the focused node through an [`embed::Input`] handle.
+//!
+//! # Example
+//!
+//! ```
+//! use moxie::state;
+//! use moxie_tui::prelude::*;
+//!
+//! let mut tui = TuiLoop::new(12, 3, || {
+//!     let (count, count_key) = state(|| 0u32);
+//!     text(format!("count: {}", count)).titled("clicks").on_key(move |key| {
+//!         if key.code == KeyCode::Char('+') {
+//!             count_key.update(|c| Some(c + 1));
+//!         }
+//!     })
+//! });
+//!
+//! tui.run_once();
+//! assert_eq!(tui.grid().to_string(), "╔ clicks ══╗\n║count: 0  ║\n╚══════════╝");
+//!
+//! tui.input().send_key('+');
+//! tui.run_once();
+//! assert_eq!(tui.grid().lines()[1], "║count: 1  ║");
+//! ```
+//!
+//! # Terminals
+//!
+//! With the `crossterm` feature enabled, [`backend::CrosstermBackend`] draws
+//! each revision to a terminal and [`event::KeyEvent::from_crossterm`]
+//! converts its keyboard events. A loop can then be spawned onto an executor
+//! with [`embed::TuiLoop::run_on_state_changes`], with terminal events sent to
+//! its [`embed::Input`].
+
+#![deny(clippy::all, missing_docs)]
+
+pub mod backend;
+pub mod embed;
+pub mod event;
+pub mod grid;
+pub mod node;
+
+/// A module for glob-importing the most commonly used moxie-tui items.
+pub mod prelude {
+    pub use crate::{
+        embed::{Input, TuiLoop},
+        event::{KeyCode, KeyEvent},
+        node::{column, list, row, text, Node},
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{grid::Grid, prelude::*};
+    use futures::{executor::LocalPool, task::LocalSpawnExt};
+    use moxie::state;
+    use std::{cell::RefCell, rc::Rc};
+
+    fn render(width: u16, height: u16, root: impl FnMut() -> Node + 'static) -> String {
+        let mut tui = TuiLoop::new(width, height, root);
+        tui.run_once();
+        let drawn = tui.grid().to_string();
+        drawn
+    }
+
+    #[test]
+    fn layout() {
+        let drawn = render(20, 6, || {
+            column(vec![
+                text("header"),
+                row(vec![list(vec!["one", "two"], Some(1)).border(), text("side\nbar")]),
+            ])
+        });
+        assert_eq!(
+            drawn,
+            "header
+┌─────┐side
+│  one│bar
+│> two│
+└─────┘"
+        );
+    }
+
+    #[test]
+    fn clipped_to_grid() {
+        assert_eq!(render(5, 1, || text("truncated\nlines")), "trunc");
+    }
+
+    #[test]
+    fn tab_moves_focus() {
+        let mut tui = TuiLoop::new(12, 8, || {
+            let (selected, select) = state(|| 0usize);
+            let items =
+                list(vec!["a", "b", "c"], Some(*selected)).titled("items").on_key(move |key| {
+                    match key.code {
+                        KeyCode::Down => {
+                            select.update(|s| Some((s + 1).min(2)));
+                        }
+                        KeyCode::Up => {
+                            select.update(|s| Some(s.saturating_sub(1)));
+                        }
+                        _ => (),
+                    }
+                });
+            column(vec![items, text("help").border().on_key(|_| ())])
+        });
+        let input = tui.input();
+
+        tui.run_once();
+        assert_eq!(tui.grid().lines()[0], "╔ items ═══╗", "first node focused by default");
+        assert_eq!(tui.grid().lines()[1], "║> a       ║");
+        assert_eq!(tui.grid().lines()[5], "┌──────────┐");
+
+        assert!(input.send_key(KeyCode::Down));
+        tui.run_once();
+        assert_eq!(tui.grid().lines()[1], "║  a       ║", "focused node receives keys");
+        assert_eq!(tui.grid().lines()[2], "║> b       ║");
+
+        assert!(input.send_key(KeyCode::Tab));
+        tui.run_once();
+        assert_eq!(tui.grid().lines()[0], "┌ items ───┐");
+        assert_eq!(tui.grid().lines()[5], "╔══════════╗", "focus moved to next node");
+
+        assert!(input.send_key(KeyCode::Down), "keys go to the newly focused node");
+        tui.run_once();
+        assert_eq!(tui.grid().lines()[2], "│> b       │", "unfocused node doesn't receive keys");
+
+        assert!(input.send_key(KeyCode::BackTab));
+        tui.run_once();
+        assert_eq!(tui.grid().lines()[0], "╔ items ═══╗", "focus wraps around");
+    }
+
+    #[test]
+    fn redrawn_on_state_changes() {
+        let mut tui = TuiLoop::new(5, 9, || {
+            let (count, count_key) = state(|| 0u32);
+            column(vec![
+                text(count.to_string()).border().on_key(move |key| {
+                    if key.code == KeyCode::Char('+') {
+                        count_key.update(|c| Some(c + 1));
+                    }
+                }),
+                text("b").border().on_key(|_| ()),
+                text("c").border().on_key(|_| ()),
+            ])
+        });
+        let presented = Rc::new(RefCell::new(Vec::new()));
+        let presented2 = presented.clone();
+        tui.set_backend(move |grid: &Grid| presented2.borrow_mut().push(grid.to_string()));
+        let input = tui.input();
+
+        let mut pool = LocalPool::new();
+        tui.set_task_executor(pool.spawner());
+        pool.spawner().spawn_local(tui.run_on_state_changes()).unwrap();
+        let mut drawn = || {
+            pool.run_until_stalled();
+            std::mem::take(&mut *presented.borrow_mut()).pop()
+        };
+
+        let first = drawn().expect("a revision runs when the loop is first polled");
+        assert_eq!(first.lines().nth(1), Some("║0  ║"));
+        assert_eq!(drawn(), None, "no revisions without state changes");
+
+        assert!(input.send_key('+'));
+        let counted = drawn().expect("a revision runs after the focused node changes state");
+        assert_eq!(counted.lines().nth(1), Some("║1  ║"));
+
+        assert!(input.send_key(KeyCode::Tab));
+        assert!(input.send_key(KeyCode::Tab), "focus moves before the next revision");
+        let tabbed = drawn().unwrap();
+        assert_eq!(tabbed.lines().nth(3), Some("┌───┐"));
+        assert_eq!(tabbed.lines().nth(6), Some("╔═══╗"), "each tab moved focus");
+    }
+}
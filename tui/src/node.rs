@@ -0,0 +1,259 @@
+//! Nodes describe the interface to draw in each revision.
+
+use crate::{
+    event::KeyEvent,
+    grid::{Grid, Rect},
+};
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    rc::Rc,
+};
+use topo::CallId;
+
+/// A handler for keyboard events delivered to a focused node.
+pub(crate) type Handler = Rc<dyn Fn(&KeyEvent)>;
+
+/// A tree of boxes, text and lists returned by the root function of a
+/// [`crate::embed::TuiLoop`] and drawn into a [`Grid`] after each revision.
+///
+/// Nodes are cheap to clone so that they can be returned from
+/// [`moxie::cache`] and friends.
+#[derive(Clone)]
+pub struct Node {
+    kind: Kind,
+    focus: Option<Focus>,
+}
+
+#[derive(Clone)]
+enum Kind {
+    Text(String),
+    Stack { direction: Direction, children: Vec<Node> },
+    Border { title: Option<String>, child: Box<Node> },
+    List { items: Vec<String>, selected: Option<usize> },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Direction {
+    Column,
+    Row,
+}
+
+#[derive(Clone)]
+struct Focus {
+    id: CallId,
+    handler: Handler,
+}
+
+/// Returns a node which draws `content`, one line per row.
+pub fn text(content: impl Into<String>) -> Node {
+    Node::new(Kind::Text(content.into()))
+}
+
+/// Returns a node which draws `children` from top to bottom.
+pub fn column(children: impl IntoIterator<Item = Node>) -> Node {
+    Node::new(Kind::Stack {
+        direction: Direction::Column,
+        children: children.into_iter().collect(),
+    })
+}
+
+/// Returns a node which draws `children` from left to right.
+pub fn row(children: impl IntoIterator<Item = Node>) -> Node {
+    Node::new(Kind::Stack { direction: Direction::Row, children: children.into_iter().collect() })
+}
+
+/// Returns a node which draws each of `items` on its own row, marking the
+/// `selected` item.
+pub fn list<Item: Into<String>>(
+    items: impl IntoIterator<Item = Item>,
+    selected: Option<usize>,
+) -> Node {
+    Node::new(Kind::List { items: items.into_iter().map(Into::into).collect(), selected })
+}
+
+impl Node {
+    fn new(kind: Kind) -> Self {
+        Self { kind, focus: None }
+    }
+
+    /// Surrounds this node with a border, which is drawn with double lines
+    /// while it or this node is focused.
+    pub fn border(self) -> Self {
+        Self::new(Kind::Border { title: None, child: Box::new(self) })
+    }
+
+    /// Surrounds this node with a border which displays `title`.
+    pub fn titled(self, title: impl Into<String>) -> Self {
+        Self::new(Kind::Border { title: Some(title.into()), child: Box::new(self) })
+    }
+
+    /// Makes this node focusable, calling `handler` with each key pressed while
+    /// it's focused.
+    ///
+    /// Focus is tracked by the [`topo::CallId`] in which this is called, so it
+    /// should be called in the same order on every revision for focus to remain
+    /// stable.
+    #[track_caller]
+    pub fn on_key(mut self, handler: impl Fn(&KeyEvent) + 'static) -> Self {
+        let id = topo::call(CallId::current);
+        self.focus = Some(Focus { id, handler: Rc::new(handler) });
+        self
+    }
+
+    /// Returns the number of columns and rows this node needs to be fully
+    /// drawn.
+    pub fn size(&self) -> (u16, u16) {
+        match &self.kind {
+            Kind::Text(content) => {
+                let width = content.lines().map(width_of).max().unwrap_or(0);
+                (width, content.lines().count() as u16)
+            }
+            Kind::Stack { direction, children } => {
+                children.iter().map(Node::size).fold((0, 0), |(w, h), (cw, ch)| match direction {
+                    Direction::Column => (w.max(cw), h.saturating_add(ch)),
+                    Direction::Row => (w.saturating_add(cw), h.max(ch)),
+                })
+            }
+            Kind::Border { title, child } => {
+                let (w, h) = child.size();
+                let title_width = title.as_deref().map_or(0, |t| width_of(t).saturating_add(2));
+                (w.max(title_width).saturating_add(2), h.saturating_add(2))
+            }
+            Kind::List { items, .. } => {
+                let width = items.iter().map(|i| width_of(i)).max().unwrap_or(0);
+                (width.saturating_add(2), items.len() as u16)
+            }
+        }
+    }
+
+    fn is_focused(&self, focused: Option<&CallId>) -> bool {
+        matches!((&self.focus, focused), (Some(f), Some(id)) if f.id == *id)
+    }
+
+    /// Draws this node into `area` of `grid`, clipping anything which doesn't
+    /// fit.
+    pub(crate) fn draw(&self, grid: &mut Grid, area: Rect, focused: Option<&CallId>) {
+        if area.is_empty() {
+            return;
+        }
+
+        match &self.kind {
+            Kind::Text(content) => {
+                for (y, line) in (area.y..area.y + area.height).zip(content.lines()) {
+                    grid.put_str(area.x, y, area.width, line);
+                }
+            }
+            Kind::Stack { direction, children } => {
+                let mut remaining = area;
+                for child in children {
+                    let (width, height) = child.size();
+                    let mut child_area = remaining;
+                    match direction {
+                        Direction::Column => {
+                            child_area.height = height.min(remaining.height);
+                            remaining.y += child_area.height;
+                            remaining.height -= child_area.height;
+                        }
+                        Direction::Row => {
+                            child_area.width = width.min(remaining.width);
+                            remaining.x += child_area.width;
+                            remaining.width -= child_area.width;
+                        }
+                    }
+                    child.draw(grid, child_area, focused);
+                }
+            }
+            Kind::Border { title, child } => {
+                let double = self.is_focused(focused) || child.is_focused(focused);
+                draw_border(grid, area, double);
+                if let Some(title) = title {
+                    let title = format!(" {} ", title);
+                    grid.put_str(area.x + 1, area.y, area.width.saturating_sub(2), &title);
+                }
+                child.draw(grid, area.inner(), focused);
+            }
+            Kind::List { items, selected } => {
+                for (i, (y, item)) in (area.y..area.y + area.height).zip(items).enumerate() {
+                    let marker = if *selected == Some(i) { "> " } else { "  " };
+                    grid.put_str(area.x, y, area.width, &format!("{}{}", marker, item));
+                }
+            }
+        }
+    }
+
+    /// Appends the focus id and handler of this node and all of its
+    /// descendants to `out` in the order they're drawn.
+    pub(crate) fn focusable(&self, out: &mut Vec<(CallId, Handler)>) {
+        if let Some(Focus { id, handler }) = &self.focus {
+            out.push((*id, handler.clone()));
+        }
+        match &self.kind {
+            Kind::Stack { children, .. } => children.iter().for_each(|c| c.focusable(out)),
+            Kind::Border { child, .. } => child.focusable(out),
+            Kind::Text(_) | Kind::List { .. } => (),
+        }
+    }
+}
+
+impl Debug for Node {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut node = match &self.kind {
+            Kind::Text(content) => {
+                let mut s = f.debug_struct("Text");
+                s.field("content", content);
+                s
+            }
+            Kind::Stack { direction, children } => {
+                let mut s = f.debug_struct(match direction {
+                    Direction::Column => "Column",
+                    Direction::Row => "Row",
+                });
+                s.field("children", children);
+                s
+            }
+            Kind::Border { title, child } => {
+                let mut s = f.debug_struct("Border");
+                s.field("title", title).field("child", child);
+                s
+            }
+            Kind::List { items, selected } => {
+                let mut s = f.debug_struct("List");
+                s.field("items", items).field("selected", selected);
+                s
+            }
+        };
+        if let Some(focus) = &self.focus {
+            node.field("focus", &focus.id);
+        }
+        node.finish()
+    }
+}
+
+fn width_of(s: &str) -> u16 {
+    s.chars().count().min(u16::MAX as usize) as u16
+}
+
+fn draw_border(grid: &mut Grid, area: Rect, double: bool) {
+    if area.width < 2 || area.height < 2 {
+        return;
+    }
+    let [horizontal, vertical, top_left, top_right, bottom_left, bottom_right] = if double {
+        ['═', '║', '╔', '╗', '╚', '╝']
+    } else {
+        ['─', '│', '┌', '┐', '└', '┘']
+    };
+
+    let (right, bottom) = (area.x + area.width - 1, area.y + area.height - 1);
+    for x in area.x + 1..right {
+        grid.set(x, area.y, horizontal);
+        grid.set(x, bottom, horizontal);
+    }
+    for y in area.y + 1..bottom {
+        grid.set(area.x, y, vertical);
+        grid.set(right, y, vertical);
+    }
+    grid.set(area.x, area.y, top_left);
+    grid.set(right, area.y, top_right);
+    grid.set(area.x, bottom, bottom_left);
+    grid.set(right, bottom, bottom_right);
+}